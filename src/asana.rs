@@ -1,24 +1,249 @@
+use std::fmt;
+
 use anyhow::{anyhow, Result};
 use reqwest::Client;
 use reqwest::StatusCode;
 use serde::Deserialize;
 use serde_json::Value;
 
+// NOTE: https://developers.asana.com/docs/input-output-options
+const TASK_OPT_FIELDS: &str = "name,resource_type,resource_subtype,completed,assignee.name,due_on,\
+    memberships.project.name,memberships.section.name,tags.name";
+// NOTE: the search API returns at most 100 tasks without pagination
+pub const SEARCH_LIMIT: usize = 100;
+// NOTE: https://developers.asana.com/docs/pagination (limit is at most 100)
+const PAGE_LIMIT: usize = 100;
+// NOTE: https://developers.asana.com/docs/get-objects-via-typeahead (count is at most 100)
+const TYPEAHEAD_COUNT: usize = 100;
+
+/// A task, or a project, portfolio, tag, user, team or section listed in place of tasks.
 #[derive(Deserialize, Debug, Clone)]
-pub(crate) struct SearchTasksData {
-    gid: String,
+pub(crate) struct Resource {
+    pub gid: String,
+    pub name: String,
+    resource_type: String,
+    #[serde(default)]
+    pub resource_subtype: String,
+    #[serde(default)]
+    pub completed: bool,
+    pub assignee: Option<Compact>,
+    pub due_on: Option<String>,
+    #[serde(default)]
+    pub memberships: Vec<Membership>,
+    #[serde(default)]
+    pub tags: Vec<Compact>,
+    // NOTE: only the resources other than tasks are searched with the permalink
+    #[serde(default)]
+    permalink_url: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct Compact {
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct Membership {
+    pub project: Option<Compact>,
+    pub section: Option<Compact>,
+}
+
+/// Type of the resources to search; the text of the prompt starting with the prefix searches
+/// the resources other than tasks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ResourceType {
+    Task,
+    Project,
+    Portfolio,
+    Tag,
+    User,
+    Team,
+}
+
+impl ResourceType {
+    const PREFIXES: [(&'static str, ResourceType); 5] = [
+        ("pf:", ResourceType::Portfolio),
+        ("p:", ResourceType::Project),
+        ("t:", ResourceType::Team),
+        ("#", ResourceType::Tag),
+        ("@", ResourceType::User),
+    ];
+
+    /// Splits the text of the prompt into the type of the resources and the query.
+    pub fn parse(text: &str) -> (Self, &str) {
+        Self::PREFIXES
+            .iter()
+            .find_map(|(prefix, r)| text.strip_prefix(prefix).map(|q| (*r, q)))
+            .unwrap_or((ResourceType::Task, text))
+    }
+
+    fn as_str(&self) -> &str {
+        match self {
+            ResourceType::Task => "task",
+            ResourceType::Project => "project",
+            ResourceType::Portfolio => "portfolio",
+            ResourceType::Tag => "tag",
+            ResourceType::User => "user",
+            ResourceType::Team => "team",
+        }
+    }
+
+    fn opt_fields(&self) -> &str {
+        match self {
+            // NOTE: users have no permalink_url
+            ResourceType::User => "name,resource_type",
+            _ => "name,resource_type,permalink_url",
+        }
+    }
+}
+
+/// Field to sort the search results by; `Relevance` leaves the order to the API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SortBy {
+    Relevance,
+    ModifiedAt,
+    DueDate,
+    CreatedAt,
+    Likes,
+}
+
+impl SortBy {
+    fn as_str(&self) -> &str {
+        match self {
+            SortBy::Relevance => "relevance",
+            SortBy::ModifiedAt => "modified_at",
+            SortBy::DueDate => "due_date",
+            SortBy::CreatedAt => "created_at",
+            SortBy::Likes => "likes",
+        }
+    }
+
+    fn next(&self) -> Self {
+        match self {
+            SortBy::Relevance => SortBy::ModifiedAt,
+            SortBy::ModifiedAt => SortBy::DueDate,
+            SortBy::DueDate => SortBy::CreatedAt,
+            SortBy::CreatedAt => SortBy::Likes,
+            SortBy::Likes => SortBy::Relevance,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Sort {
+    pub by: SortBy,
+    pub ascending: bool,
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Sort {
+            by: SortBy::Relevance,
+            ascending: false,
+        }
+    }
+}
+
+impl Sort {
+    /// The sort of my tasks listed before the first search: the last modified first.
+    pub const MY_TASKS: Sort = Sort {
+        by: SortBy::ModifiedAt,
+        ascending: false,
+    };
+
+    pub fn next(self) -> Self {
+        Sort {
+            by: self.by.next(),
+            ..self
+        }
+    }
+
+    pub fn reverse(self) -> Self {
+        Sort {
+            ascending: !self.ascending,
+            ..self
+        }
+    }
+
+    fn query(&self) -> Vec<(&str, String)> {
+        match self.by {
+            SortBy::Relevance => Vec::new(),
+            by => vec![
+                ("sort_by", by.as_str().to_string()),
+                ("sort_ascending", self.ascending.to_string()),
+            ],
+        }
+    }
+}
+
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.by {
+            SortBy::Relevance => write!(f, "{}", self.by.as_str()),
+            by if self.ascending => write!(f, "{} ↑", by.as_str()),
+            by => write!(f, "{} ↓", by.as_str()),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct Resources {
+    pub data: Vec<Resource>,
+}
+
+// NOTE: https://developers.asana.com/docs/pagination
+#[derive(Deserialize, Debug)]
+struct Page {
+    data: Vec<Resource>,
+    next_page: Option<NextPage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct NextPage {
+    offset: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct WorkspacesData {
+    pub gid: String,
     pub name: String,
     #[allow(dead_code)]
     resource_type: String,
 }
 
 #[derive(Deserialize, Debug)]
-pub(crate) struct SearchTasks {
-    pub data: Vec<SearchTasksData>,
+pub(crate) struct Workspace {
+    pub data: WorkspacesData,
 }
 
-impl SearchTasksData {
+#[derive(Deserialize, Debug)]
+pub(crate) struct Workspaces {
+    pub data: Vec<WorkspacesData>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct UsersMeData {
+    #[allow(dead_code)]
+    pub gid: String,
+    pub name: String,
+    pub email: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub(crate) struct UsersMe {
+    pub data: UsersMeData,
+}
+
+impl Resource {
     pub async fn get_permalink_url(&self, pats: &str) -> Result<String> {
+        match (self.resource_type.as_str(), &self.permalink_url) {
+            ("task", _) => {}
+            (_, Some(url)) => return Ok(url.clone()),
+            // NOTE: the profile of a user, which has no permalink_url
+            ("user", None) => return Ok(format!("https://app.asana.com/0/profile/{}", self.gid)),
+            (r, None) => return Err(anyhow!("Failed to get permalink_url of the {}", r)),
+        }
+
         let json = self.do_get_permalink_url(pats).await?;
         let root: Value = serde_json::from_str(&json)?;
         root.get("data")
@@ -35,52 +260,336 @@ impl SearchTasksData {
         // NOTE: https://developers.asana.com/docs/personal-access-token
         let res = cli.get(url).bearer_auth(pats).send().await?;
         if res.status() != StatusCode::OK {
-            return Err(anyhow!("Failed to get task in a workspace app.asana.com"));
+            return Err(anyhow!(
+                "Failed to get task in a workspace app.asana.com ({})",
+                res.status()
+            ));
         }
 
         Ok(res.text().await?)
     }
+
+    pub fn is_task(&self) -> bool {
+        self.resource_type == "task"
+    }
+
+    /// Returns whether the resource has a URL to output; a section has none.
+    pub fn has_url(&self) -> bool {
+        self.resource_type != "section"
+    }
+
+    /// Lists the children in the workspace tree: the projects of a team, the sections of a
+    /// project or the tasks of a section.
+    pub async fn get_children(&self, pats: &str) -> Result<Resources> {
+        let (path, query, opt_fields) = match self.resource_type.as_str() {
+            // NOTE: https://developers.asana.com/docs/get-a-team-s-projects
+            "team" => (
+                format!("teams/{}/projects", self.gid),
+                &[("archived", "false")][..],
+                "name,resource_type,permalink_url",
+            ),
+            // NOTE: https://developers.asana.com/docs/get-sections-in-a-project
+            "project" => (
+                format!("projects/{}/sections", self.gid),
+                &[][..],
+                "name,resource_type",
+            ),
+            // NOTE: https://developers.asana.com/docs/get-tasks-from-a-section
+            "section" => (
+                format!("sections/{}/tasks", self.gid),
+                &[][..],
+                TASK_OPT_FIELDS,
+            ),
+            r => return Err(anyhow!("Failed to list the children of the {}", r)),
+        };
+
+        do_list(&path, query, opt_fields, pats).await
+    }
+}
+
+pub(crate) async fn get_teams(workspace_gid: &str, pats: &str) -> Result<Resources> {
+    // NOTE: https://developers.asana.com/docs/get-teams-in-a-workspace
+    let path = format!("workspaces/{}/teams", workspace_gid);
+    do_list(&path, &[], "name,resource_type,permalink_url", pats).await
+}
+
+/// Lists all the resources by following the pages.
+async fn do_list(
+    path: &str,
+    query: &[(&str, &str)],
+    opt_fields: &str,
+    pats: &str,
+) -> Result<Resources> {
+    let url = format!("https://app.asana.com/api/1.0/{}", path);
+    let cli = Client::new();
+    let mut data = Vec::new();
+    let mut offset = None;
+    loop {
+        // NOTE: https://developers.asana.com/docs/personal-access-token
+        let mut req = cli
+            .get(&url)
+            .query(query)
+            .query(&[
+                ("opt_fields", opt_fields.to_string()),
+                ("limit", PAGE_LIMIT.to_string()),
+            ])
+            .bearer_auth(pats);
+        if let Some(offset) = &offset {
+            req = req.query(&[("offset", offset)]);
+        }
+        let res = req.send().await?;
+        if res.status() != StatusCode::OK {
+            return Err(anyhow!(
+                "Failed to list resources in a workspace app.asana.com ({})",
+                res.status()
+            ));
+        }
+
+        let page: Page = serde_json::from_str(&res.text().await?)?;
+        data.extend(page.data);
+        match page.next_page {
+            Some(next_page) => offset = Some(next_page.offset),
+            None => break,
+        }
+    }
+
+    Ok(Resources { data })
 }
 
 pub(crate) async fn search_tasks(
     workspace_gid: &str,
     text: &str,
+    sort: Sort,
     pats: &str,
-) -> Result<SearchTasks> {
-    let json = do_search_tasks(workspace_gid, text, pats).await?;
-    let tasks: SearchTasks = serde_json::from_str(&json)?;
+) -> Result<Resources> {
+    let mut query = vec![("text", text.to_string())];
+    query.extend(sort.query());
+    let json = do_search_tasks(workspace_gid, &query, pats).await?;
+    let tasks: Resources = serde_json::from_str(&json)?;
 
     Ok(tasks)
 }
 
-async fn do_search_tasks(workspace_gid: &str, text: &str, pats: &str) -> Result<String> {
+/// Searches the incomplete tasks assigned to me.
+pub(crate) async fn search_my_tasks(
+    workspace_gid: &str,
+    sort: Sort,
+    pats: &str,
+) -> Result<Resources> {
+    let mut query = vec![
+        ("assignee.any", "me".to_string()),
+        ("completed", "false".to_string()),
+    ];
+    query.extend(sort.query());
+    let json = do_search_tasks(workspace_gid, &query, pats).await?;
+    let tasks: Resources = serde_json::from_str(&json)?;
+
+    Ok(tasks)
+}
+
+async fn do_search_tasks(
+    workspace_gid: &str,
+    query: &[(&str, String)],
+    pats: &str,
+) -> Result<String> {
     // NOTE: https://developers.asana.com/docs/search-tasks-in-a-workspace
     let url = format!(
-        "https://app.asana.com/api/1.0/workspaces/{}/tasks/search?text={}",
-        workspace_gid, text
+        "https://app.asana.com/api/1.0/workspaces/{}/tasks/search",
+        workspace_gid
     );
     let cli = Client::new();
     // NOTE: https://developers.asana.com/docs/personal-access-token
-    let res = cli.get(url).bearer_auth(pats).send().await?;
+    let res = cli
+        .get(url)
+        .query(query)
+        .query(&[
+            ("opt_fields", TASK_OPT_FIELDS.to_string()),
+            ("limit", SEARCH_LIMIT.to_string()),
+        ])
+        .bearer_auth(pats)
+        .send()
+        .await?;
     if res.status() != StatusCode::OK {
         return Err(anyhow!(
-            "Failed to search tasks in a workspace app.asana.com"
+            "Failed to search tasks in a workspace app.asana.com ({})",
+            res.status()
         ));
     }
 
     Ok(res.text().await?)
 }
 
-pub(crate) async fn get_workspace(workspace_gid: &str, pats: &str) -> Result<bool> {
+pub(crate) async fn typeahead(
+    workspace_gid: &str,
+    resource_type: ResourceType,
+    query: &str,
+    pats: &str,
+) -> Result<Resources> {
+    let json = do_typeahead(workspace_gid, resource_type, query, pats).await?;
+    let resources: Resources = serde_json::from_str(&json)?;
+
+    Ok(resources)
+}
+
+async fn do_typeahead(
+    workspace_gid: &str,
+    resource_type: ResourceType,
+    query: &str,
+    pats: &str,
+) -> Result<String> {
+    // NOTE: https://developers.asana.com/docs/get-objects-via-typeahead
+    let url = format!(
+        "https://app.asana.com/api/1.0/workspaces/{}/typeahead",
+        workspace_gid
+    );
+    let cli = Client::new();
+    // NOTE: https://developers.asana.com/docs/personal-access-token
+    let res = cli
+        .get(url)
+        .query(&[
+            ("resource_type", resource_type.as_str()),
+            ("query", query),
+            ("count", &TYPEAHEAD_COUNT.to_string()),
+            ("opt_fields", resource_type.opt_fields()),
+        ])
+        .bearer_auth(pats)
+        .send()
+        .await?;
+    if res.status() != StatusCode::OK {
+        return Err(anyhow!(
+            "Failed to search {}s in a workspace app.asana.com ({})",
+            resource_type.as_str(),
+            res.status()
+        ));
+    }
+
+    Ok(res.text().await?)
+}
+
+pub(crate) async fn get_workspace(workspace_gid: &str, pats: &str) -> Result<Workspace> {
+    let json = do_get_workspace(workspace_gid, pats).await?;
+    let workspace: Workspace = serde_json::from_str(&json)?;
+
+    Ok(workspace)
+}
+
+async fn do_get_workspace(workspace_gid: &str, pats: &str) -> Result<String> {
     // NOTE: https://developers.asana.com/docs/get-a-workspace
     let url = format!("https://app.asana.com/api/1.0/workspaces/{}", workspace_gid);
     let cli = Client::new();
     // NOTE: https://developers.asana.com/docs/personal-access-token
     let res = cli.get(url).bearer_auth(pats).send().await?;
+    if res.status() != StatusCode::OK {
+        return Err(anyhow!("Failed to access workspace app.asana.com"));
+    }
+
+    Ok(res.text().await?)
+}
 
+pub(crate) async fn get_workspaces(pats: &str) -> Result<Workspaces> {
+    let json = do_get_workspaces(pats).await?;
+    let workspaces: Workspaces = serde_json::from_str(&json)?;
+
+    Ok(workspaces)
+}
+
+async fn do_get_workspaces(pats: &str) -> Result<String> {
+    // NOTE: https://developers.asana.com/docs/get-multiple-workspaces
+    let url = "https://app.asana.com/api/1.0/workspaces";
+    let cli = Client::new();
+    // NOTE: https://developers.asana.com/docs/personal-access-token
+    let res = cli.get(url).bearer_auth(pats).send().await?;
     if res.status() != StatusCode::OK {
-        return Err(anyhow!("Failed to access me"));
+        return Err(anyhow!("Failed to get workspaces app.asana.com"));
     }
 
-    Ok(true)
+    Ok(res.text().await?)
+}
+
+pub(crate) async fn get_me(pats: &str) -> Result<UsersMe> {
+    let json = do_get_me(pats).await?;
+    let me: UsersMe = serde_json::from_str(&json)?;
+
+    Ok(me)
+}
+
+async fn do_get_me(pats: &str) -> Result<String> {
+    // NOTE: https://developers.asana.com/docs/get-a-user
+    let url = "https://app.asana.com/api/1.0/users/me";
+    let cli = Client::new();
+    // NOTE: https://developers.asana.com/docs/personal-access-token
+    let res = cli.get(url).bearer_auth(pats).send().await?;
+    if res.status() != StatusCode::OK {
+        return Err(anyhow!("Failed to access me app.asana.com"));
+    }
+
+    Ok(res.text().await?)
+}
+
+pub(crate) async fn has_premium_search(workspace_gid: &str, pats: &str) -> Result<bool> {
+    // NOTE: https://developers.asana.com/docs/search-tasks-in-a-workspace
+    let url = format!(
+        "https://app.asana.com/api/1.0/workspaces/{}/tasks/search?limit=1",
+        workspace_gid
+    );
+    let cli = Client::new();
+    // NOTE: https://developers.asana.com/docs/personal-access-token
+    let res = cli.get(url).bearer_auth(pats).send().await?;
+    match res.status() {
+        StatusCode::OK => Ok(true),
+        // NOTE: the search API returns 402 Payment Required in a non-premium workspace
+        StatusCode::PAYMENT_REQUIRED => Ok(false),
+        _ => Err(anyhow!(
+            "Failed to search tasks in a workspace app.asana.com"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resource_type_parse_prefixes() {
+        assert_eq!(
+            ResourceType::parse("pf:roadmap"),
+            (ResourceType::Portfolio, "roadmap")
+        );
+        assert_eq!(
+            ResourceType::parse("p:website"),
+            (ResourceType::Project, "website")
+        );
+        assert_eq!(ResourceType::parse("t:eng"), (ResourceType::Team, "eng"));
+        assert_eq!(
+            ResourceType::parse("#urgent"),
+            (ResourceType::Tag, "urgent")
+        );
+        assert_eq!(ResourceType::parse("@alice"), (ResourceType::User, "alice"));
+    }
+
+    #[test]
+    fn resource_type_parse_tasks_without_a_prefix() {
+        assert_eq!(
+            ResourceType::parse("fix login"),
+            (ResourceType::Task, "fix login")
+        );
+        assert_eq!(ResourceType::parse(""), (ResourceType::Task, ""));
+        // NOTE: the prefix is only at the start of the text
+        assert_eq!(
+            ResourceType::parse("fix p:login"),
+            (ResourceType::Task, "fix p:login")
+        );
+    }
+
+    #[test]
+    fn sort_query() {
+        assert!(Sort::default().query().is_empty());
+        assert_eq!(
+            Sort::default().next().reverse().query(),
+            [
+                ("sort_by", "modified_at".to_string()),
+                ("sort_ascending", "true".to_string())
+            ]
+        );
+    }
 }
@@ -1,8 +1,21 @@
-use clap::{crate_description, crate_name, crate_version, Arg, Command};
+use clap::{crate_description, crate_name, crate_version, Arg, ArgMatches, Command};
+
+use crate::config;
+use crate::render;
 
 pub const WORKSPACE_GID: &str = "workspace_gid";
 pub const PATS: &str = "pats";
 pub const FILE: &str = "file";
+const LEGACY_PATS: &str = "legacy_pats";
+const LEGACY_FILE: &str = "legacy_file";
+pub const PROFILE: &str = "profile";
+pub const HEIGHT: &str = "height";
+pub const WORKSPACES: &str = "workspaces";
+pub const INIT: &str = "init";
+pub const DOCTOR: &str = "doctor";
+pub const FORGET: &str = "forget";
+pub const GIDS: &str = "gids";
+pub const ALL: &str = "all";
 
 pub(crate) fn build() -> Command<'static> {
     Command::new(crate_name!())
@@ -10,13 +23,83 @@ pub(crate) fn build() -> Command<'static> {
         .about(crate_description!())
         .arg(
             Arg::new(WORKSPACE_GID)
+                .short('w')
+                .long("workspace-gid")
                 .help("Globally unique identifier for the workspace or organization")
-                .required(true),
+                .takes_value(true)
+                .required(false),
         )
         .arg(
             Arg::new(PATS)
+                .short('p')
+                .long("pats")
                 .help("Personal Access Tokens (PATs)")
-                .required(true),
+                .takes_value(true)
+                .global(true)
+                .required(false),
+        )
+        .arg(
+            Arg::new(PROFILE)
+                .long("profile")
+                .help("Profile name in the config file")
+                .takes_value(true)
+                .global(true)
+                .default_value(config::DEFAULT_PROFILE),
+        )
+        .arg(
+            Arg::new(HEIGHT)
+                .long("height")
+                .help("Display below the cursor with the given height instead of fullscreen, e.g. 20 or 40%")
+                .takes_value(true)
+                .validator(|s| s.parse::<render::Height>())
+                .required(false),
         )
         .arg(Arg::new(FILE).help("Output file").required(false))
+        // NOTE: keep `asaru <workspace_gid> <pats> [file]` of the earlier versions working
+        .arg(
+            Arg::new(LEGACY_PATS)
+                .hide(true)
+                .required(false)
+                .conflicts_with_all(&[WORKSPACE_GID, PATS]),
+        )
+        .arg(Arg::new(LEGACY_FILE).hide(true).required(false))
+        .subcommand(Command::new(WORKSPACES).about("List accessible workspaces"))
+        .subcommand(Command::new(INIT).about("Set up PATs and a workspace in the config file"))
+        .subcommand(
+            Command::new(DOCTOR)
+                .about("Diagnose the network, PATs, workspace, terminal and config file")
+                .arg(
+                    Arg::new(WORKSPACE_GID)
+                        .short('w')
+                        .long("workspace-gid")
+                        .help("Globally unique identifier for the workspace or organization")
+                        .takes_value(true)
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new(FORGET)
+                .about("Forget tasks in the ranking of frequently and recently used tasks")
+                .arg(
+                    Arg::new(GIDS)
+                        .help("Globally unique identifiers for the tasks")
+                        .multiple_values(true)
+                        .required_unless_present(ALL),
+                )
+                .arg(
+                    Arg::new(ALL)
+                        .long("all")
+                        .help("Forget all the tasks")
+                        .conflicts_with(GIDS),
+                ),
+        )
+}
+
+/// Returns the workspace_gid, the pats and the output file given as the positional arguments,
+/// which are `[file]` or `<workspace_gid> <pats> [file]` of the earlier versions.
+pub(crate) fn positionals(m: &ArgMatches) -> (Option<&str>, Option<&str>, Option<&str>) {
+    match m.value_of(LEGACY_PATS) {
+        Some(pats) => (m.value_of(FILE), Some(pats), m.value_of(LEGACY_FILE)),
+        None => (None, None, m.value_of(FILE)),
+    }
 }
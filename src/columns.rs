@@ -0,0 +1,277 @@
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::asana;
+use crate::render;
+
+const SEPARATOR: &str = "  ";
+const CHECKMARK: &str = "✓";
+const MILESTONE_MARKER: &str = "◆ ";
+const APPROVAL_MARKER: &str = "⊕ ";
+const MAX_WIDTH: usize = 20;
+const MIN_NAME_WIDTH: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Column {
+    Completed,
+    Name,
+    Assignee,
+    Due,
+    Project,
+    Section,
+    Tags,
+}
+
+pub const DEFAULT_COLUMNS: [Column; 4] = [
+    Column::Completed,
+    Column::Name,
+    Column::Assignee,
+    Column::Due,
+];
+
+/// Widths of the columns fitted to the terminal; the name column takes the rest.
+pub(crate) struct Layout {
+    columns: Vec<(Column, usize)>,
+    today: NaiveDate,
+}
+
+impl Layout {
+    pub fn new(columns: &[Column], tasks: &[asana::Resource], width: usize) -> Self {
+        let today = Local::now().date_naive();
+        let mut columns = columns
+            .iter()
+            .map(|&c| {
+                let w = tasks
+                    .iter()
+                    .map(|t| render::width(&cell(c, t, today)))
+                    .max()
+                    .unwrap_or(0);
+                (c, w.min(MAX_WIDTH))
+            })
+            // NOTE: hide the columns that no task has a value for
+            .filter(|&(c, w)| c == Column::Name || w > 0)
+            .collect::<Vec<_>>();
+
+        // NOTE: drop the rightmost columns until the name column has room
+        loop {
+            let others = columns
+                .iter()
+                .filter(|(c, _)| *c != Column::Name)
+                .map(|(_, w)| w + SEPARATOR.len())
+                .sum::<usize>();
+            let droppable = columns.iter().rposition(|(c, _)| *c != Column::Name);
+            match droppable {
+                Some(i) if others + MIN_NAME_WIDTH > width => {
+                    columns.remove(i);
+                }
+                _ => {
+                    let name_width = width.saturating_sub(others);
+                    columns
+                        .iter_mut()
+                        .filter(|(c, _)| *c == Column::Name)
+                        .for_each(|(_, w)| *w = name_width);
+                    break;
+                }
+            }
+        }
+
+        Layout { columns, today }
+    }
+
+    /// Returns the row of `task` with the name scrolled by `hscroll` grapheme clusters.
+    pub fn row(&self, task: &asana::Resource, hscroll: usize) -> String {
+        self.columns
+            .iter()
+            .map(|&(c, w)| {
+                let s = match c {
+                    Column::Name => render::scroll(&name(task), hscroll, w),
+                    _ => render::truncate(&cell(c, task, self.today), w),
+                };
+                format!("{}{}", s, " ".repeat(w.saturating_sub(render::width(&s))))
+            })
+            .collect::<Vec<_>>()
+            .join(SEPARATOR)
+            .trim_end()
+            .to_string()
+    }
+
+    /// Returns whether the name of `task` scrolled by `hscroll` is still truncated.
+    pub fn name_overflows(&self, task: &asana::Resource, hscroll: usize) -> bool {
+        self.columns
+            .iter()
+            .any(|&(c, w)| c == Column::Name && render::overflows(&name(task), hscroll, w))
+    }
+
+    /// Returns whether `task` is open and its due date has passed.
+    pub fn is_overdue(&self, task: &asana::Resource) -> bool {
+        !task.completed && due_date(task).map(|d| d < self.today).unwrap_or(false)
+    }
+}
+
+fn cell(column: Column, task: &asana::Resource, today: NaiveDate) -> String {
+    match column {
+        Column::Completed if task.completed => CHECKMARK.to_string(),
+        Column::Completed => String::new(),
+        Column::Name => name(task),
+        Column::Assignee => task
+            .assignee
+            .as_ref()
+            .map(|a| a.name.clone())
+            .unwrap_or_default(),
+        Column::Due => due_date(task)
+            .map(|d| relative_date(d, today))
+            .unwrap_or_default(),
+        Column::Project => task
+            .memberships
+            .iter()
+            .find_map(|m| m.project.as_ref())
+            .map(|p| p.name.clone())
+            .unwrap_or_default(),
+        Column::Section => task
+            .memberships
+            .iter()
+            .find_map(|m| m.section.as_ref())
+            .map(|s| s.name.clone())
+            .unwrap_or_default(),
+        Column::Tags => task
+            .tags
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+// NOTE: https://developers.asana.com/docs/tasks (resource_subtype)
+fn name(task: &asana::Resource) -> String {
+    match task.resource_subtype.as_str() {
+        "milestone" => format!("{}{}", MILESTONE_MARKER, task.name),
+        "approval" => format!("{}{}", APPROVAL_MARKER, task.name),
+        _ => task.name.clone(),
+    }
+}
+
+fn due_date(task: &asana::Resource) -> Option<NaiveDate> {
+    task.due_on
+        .as_deref()
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+}
+
+fn relative_date(date: NaiveDate, today: NaiveDate) -> String {
+    match (date - today).num_days() {
+        0 => "today".to_string(),
+        1 => "tomorrow".to_string(),
+        -1 => "yesterday".to_string(),
+        d if d > 0 => format!("in {}d", d),
+        d => format!("{}d ago", -d),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn task(name: &str, assignee: Option<&str>) -> asana::Resource {
+        serde_json::from_value(json!({
+            "gid": "1",
+            "name": name,
+            "resource_type": "task",
+            "assignee": assignee.map(|a| json!({ "name": a })),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn layout_gives_the_rest_to_the_name() {
+        let tasks = [task("Fix login bug", Some("Alice"))];
+        let layout = Layout::new(&[Column::Name, Column::Assignee], &tasks, 30);
+        assert_eq!(
+            layout.row(&tasks[0], 0),
+            format!("{:<23}  Alice", "Fix login bug")
+        );
+    }
+
+    #[test]
+    fn layout_hides_the_columns_without_values() {
+        let tasks = [task("Fix login bug", None)];
+        let layout = Layout::new(&DEFAULT_COLUMNS, &tasks, 30);
+        assert_eq!(layout.row(&tasks[0], 0), "Fix login bug");
+    }
+
+    #[test]
+    fn layout_drops_the_rightmost_columns_when_narrow() {
+        let tasks = [task("Fix login bug", Some("Bob Smith"))];
+        let layout = Layout::new(&[Column::Name, Column::Assignee], &tasks, 20);
+        assert_eq!(layout.row(&tasks[0], 0), "Fix login bug");
+        let layout = Layout::new(&[Column::Name, Column::Assignee], &tasks, 27);
+        assert_eq!(layout.row(&tasks[0], 0), "Fix login bug     Bob Smith");
+    }
+
+    #[test]
+    fn layout_truncates_and_scrolls_the_name() {
+        let tasks = [task("Refactor the parser for better errors", None)];
+        let layout = Layout::new(&[Column::Name], &tasks, 20);
+        assert_eq!(layout.row(&tasks[0], 0), "Refactor the parser…");
+        assert!(layout.name_overflows(&tasks[0], 0));
+        assert_eq!(layout.row(&tasks[0], 18), "…r for better errors");
+        assert!(!layout.name_overflows(&tasks[0], 18));
+    }
+
+    #[test]
+    fn relative_date_to_today() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let day = |d| NaiveDate::from_ymd_opt(2024, 2, d).unwrap();
+        assert_eq!(relative_date(today, today), "today");
+        assert_eq!(relative_date(today.succ_opt().unwrap(), today), "tomorrow");
+        assert_eq!(relative_date(day(29), today), "yesterday");
+        assert_eq!(relative_date(day(20), today), "10d ago");
+        assert_eq!(
+            relative_date(NaiveDate::from_ymd_opt(2024, 3, 8).unwrap(), today),
+            "in 7d"
+        );
+    }
+
+    fn task_with(fields: serde_json::Value) -> asana::Resource {
+        let mut v = json!({ "gid": "1", "name": "Launch", "resource_type": "task" });
+        v.as_object_mut()
+            .unwrap()
+            .extend(fields.as_object().unwrap().clone());
+        serde_json::from_value(v).unwrap()
+    }
+
+    #[test]
+    fn name_marks_milestones_and_approvals() {
+        let milestone = task_with(json!({ "resource_subtype": "milestone" }));
+        let approval = task_with(json!({ "resource_subtype": "approval" }));
+        let default = task_with(json!({ "resource_subtype": "default_task" }));
+        assert_eq!(name(&milestone), "◆ Launch");
+        assert_eq!(name(&approval), "⊕ Launch");
+        assert_eq!(name(&default), "Launch");
+    }
+
+    #[test]
+    fn is_overdue_only_if_open_and_past_due() {
+        let today = Local::now().date_naive();
+        let yesterday = today.pred_opt().unwrap().to_string();
+        let overdue = task_with(json!({ "due_on": yesterday }));
+        let completed = task_with(json!({ "due_on": yesterday, "completed": true }));
+        let due_today = task_with(json!({ "due_on": today.to_string() }));
+        let no_due = task_with(json!({}));
+        let layout = Layout::new(&DEFAULT_COLUMNS, &[], 80);
+        assert!(layout.is_overdue(&overdue));
+        assert!(!layout.is_overdue(&completed));
+        assert!(!layout.is_overdue(&due_today));
+        assert!(!layout.is_overdue(&no_due));
+    }
+
+    #[test]
+    fn completed_column_shows_a_checkmark() {
+        let today = Local::now().date_naive();
+        let completed = task_with(json!({ "completed": true }));
+        assert_eq!(cell(Column::Completed, &completed, today), "✓");
+        assert_eq!(cell(Column::Completed, &task_with(json!({})), today), "");
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::columns;
+use crate::controller;
+use crate::keymap;
+use crate::theme;
+
+pub const DEFAULT_PROFILE: &str = "default";
+const CONFIG_DIR: &str = ".asaru";
+const CONFIG_FILE: &str = "config.toml";
+const DIR_MODE: u32 = 0o700;
+const FILE_MODE: u32 = 0o600;
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub(crate) struct Config {
+    pub theme: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<columns::Column>,
+    #[serde(default)]
+    pub output_order: controller::OutputOrder,
+    #[serde(default)]
+    pub keys: keymap::KeysConfig,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub themes: BTreeMap<String, theme::ThemeConfig>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct Profile {
+    pub pats: Option<String>,
+    pub workspace_gid: Option<String>,
+}
+
+impl Config {
+    pub fn load() -> Result<Self> {
+        read_toml(&path()?)
+    }
+
+    /// Saves the profile by adding or replacing its table in the config file, keeping the rest
+    /// of the file such as the comments as it is.
+    pub fn save_profile(&self, name: &str) -> Result<PathBuf> {
+        let path = path()?;
+        let profile = self.profile(name);
+        let s = if path.exists() {
+            fs::read_to_string(&path)?
+        } else {
+            String::new()
+        };
+        let table = toml::to_string(&ProfileTable {
+            profiles: BTreeMap::from([(name, &profile)]),
+        })?;
+        let s = replace_table(&s, &table);
+        // NOTE: rewrite the whole file if the profile is written otherwise, e.g. as an inline table
+        let s = match toml::from_str::<Config>(&s) {
+            Ok(config) if config.profile(name) == profile => s,
+            _ => toml::to_string(self)?,
+        };
+        write_private(&path, &s)?;
+
+        Ok(path)
+    }
+
+    pub fn profile(&self, name: &str) -> Profile {
+        self.profiles.get(name).cloned().unwrap_or_default()
+    }
+}
+
+#[derive(Serialize)]
+struct ProfileTable<'a> {
+    profiles: BTreeMap<&'a str, &'a Profile>,
+}
+
+/// Replaces the table of the same header as `table` in `s`, or appends `table` to `s`.
+fn replace_table(s: &str, table: &str) -> String {
+    let header = table.lines().next().unwrap_or_default();
+    let lines = s.lines().collect::<Vec<_>>();
+    let start = match lines.iter().position(|l| l.trim() == header) {
+        Some(start) => start,
+        None if s.trim().is_empty() => return table.to_string(),
+        None => return format!("{}\n\n{}", s.trim_end(), table),
+    };
+    let mut end = lines[start + 1..]
+        .iter()
+        .position(|l| l.trim_start().starts_with('['))
+        .map(|i| start + 1 + i)
+        .unwrap_or(lines.len());
+    // NOTE: keep the blank lines and the comments before the next table
+    while end > start + 1 && {
+        let l = lines[end - 1].trim_start();
+        l.is_empty() || l.starts_with('#')
+    } {
+        end -= 1;
+    }
+
+    let mut t = lines[..start].join("\n");
+    if start > 0 {
+        t.push('\n');
+    }
+    t.push_str(table);
+    for l in &lines[end..] {
+        t.push_str(l);
+        t.push('\n');
+    }
+
+    t
+}
+
+pub(crate) fn path() -> Result<PathBuf> {
+    dir().map(|dir| dir.join(CONFIG_FILE))
+}
+
+/// Reads the TOML file, or returns the default if the file does not exist.
+pub(crate) fn read_toml<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+
+    let s = fs::read_to_string(path)?;
+    toml::from_str(&s).map_err(|e| anyhow!("Failed to parse \"{}\": {}", path.display(), e))
+}
+
+/// Writes the file readable only by the user, creating the directory if needed.
+pub(crate) fn write_private(path: &Path, s: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        DirBuilder::new()
+            .recursive(true)
+            .mode(DIR_MODE)
+            .create(dir)?;
+    }
+
+    let mut f = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .mode(FILE_MODE)
+        .open(path)?;
+    // NOTE: `mode` is applied only when the file is newly created
+    f.set_permissions(fs::Permissions::from_mode(FILE_MODE))?;
+    f.write_all(s.as_bytes())?;
+
+    Ok(())
+}
+
+/// Returns the directory of the config file, which also keeps the other files of `asaru`.
+pub(crate) fn dir() -> Result<PathBuf> {
+    dirs::home_dir()
+        .map(|home| home.join(CONFIG_DIR))
+        .ok_or(anyhow!("Failed to find home directory"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: &str = "[profiles.work]\npats = \"new\"\n";
+
+    #[test]
+    fn replace_table_in_an_empty_file() {
+        assert_eq!(replace_table("", TABLE), TABLE);
+    }
+
+    #[test]
+    fn replace_table_appends_a_new_table() {
+        let s = "# my theme\ntheme = \"dark\"\n";
+        assert_eq!(
+            replace_table(s, TABLE),
+            "# my theme\ntheme = \"dark\"\n\n[profiles.work]\npats = \"new\"\n"
+        );
+    }
+
+    #[test]
+    fn replace_table_keeps_the_other_tables_and_comments() {
+        let s = "\
+# keys
+[keys.results]
+\"C-j\" = \"move-down\"
+
+[profiles.work]
+pats = \"old\"
+workspace_gid = \"1\"
+
+# personal
+[profiles.home]
+pats = \"home\"
+";
+        assert_eq!(
+            replace_table(s, TABLE),
+            "\
+# keys
+[keys.results]
+\"C-j\" = \"move-down\"
+
+[profiles.work]
+pats = \"new\"
+
+# personal
+[profiles.home]
+pats = \"home\"
+"
+        );
+    }
+
+    #[test]
+    fn replace_table_at_the_end() {
+        let s = "theme = \"dark\"\n\n[profiles.work]\npats = \"old\"\n";
+        assert_eq!(
+            replace_table(s, TABLE),
+            "theme = \"dark\"\n\n[profiles.work]\npats = \"new\"\n"
+        );
+    }
+}
@@ -1,133 +1,644 @@
-use std::collections::HashSet;
+use std::cmp::{max, min, Reverse};
+use std::collections::HashMap;
+use std::mem;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use futures::future;
+use serde::{Deserialize, Serialize};
 
 use crate::asana;
+use crate::columns;
+use crate::editor::LineEditor;
+use crate::frecency::Frecency;
+
+/// Order of the URLs of the selected tasks in the output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum OutputOrder {
+    /// The order in which the tasks were selected.
+    #[default]
+    Selection,
+    /// The order of the search results; the tasks selected in other searches follow.
+    Screen,
+}
 
 pub(crate) struct State {
     workspace_gid: String,
+    workspace_name: String,
+    profile: String,
     pats: String,
-    text: String,
-    tasks: Vec<asana::SearchTasksData>,
+    columns: Vec<columns::Column>,
+    sort: asana::Sort,
+    editor: LineEditor,
+    tasks: Vec<asana::Resource>,
+    workspaces: Vec<asana::WorkspacesData>,
     index: usize,
-    checked: HashSet<usize>,
+    offset: usize,
+    hscroll: usize,
+    // NOTE: selected tasks kept across searches in the order of selection
+    basket: Vec<asana::Resource>,
+    permalinks: HashMap<String, String>,
+    failures: Vec<(asana::Resource, String)>,
+    mark: Option<usize>,
+    error: Option<String>,
+    // NOTE: the duration of the last search, `None` before the first search
+    latency: Option<Duration>,
+    // NOTE: whether the results are my tasks listed before the first search
+    my_tasks: bool,
+    frecency: Frecency,
+    // NOTE: the listings to go back to in the browse mode with the index in each
+    browse: Vec<(Vec<asana::Resource>, usize)>,
+    // NOTE: the teams, projects and sections opened in the browse mode
+    path: Vec<asana::Resource>,
 }
 
 impl State {
-    pub fn text(&self) -> &str {
-        &self.text
+    pub fn workspace_name(&self) -> &str {
+        &self.workspace_name
+    }
+
+    pub fn profile(&self) -> &str {
+        &self.profile
+    }
+
+    pub fn text(&self) -> String {
+        self.editor.text()
     }
 
-    pub fn tasks(&self) -> &[asana::SearchTasksData] {
+    pub fn editor(&self) -> &LineEditor {
+        &self.editor
+    }
+
+    pub fn tasks(&self) -> &[asana::Resource] {
         &self.tasks
     }
 
+    pub fn workspaces(&self) -> &[asana::WorkspacesData] {
+        &self.workspaces
+    }
+
     pub fn index(&self) -> usize {
         self.index
     }
 
-    pub fn checked(&self) -> &HashSet<usize> {
-        &self.checked
+    pub fn offset(&self) -> usize {
+        self.offset
     }
 
-    pub fn new(workspace_gid: &str, pats: &str) -> Self {
+    pub fn sort(&self) -> asana::Sort {
+        self.sort
+    }
+
+    pub fn hscroll(&self) -> usize {
+        self.hscroll
+    }
+
+    pub fn basket(&self) -> &[asana::Resource] {
+        &self.basket
+    }
+
+    pub fn failures(&self) -> &[(asana::Resource, String)] {
+        &self.failures
+    }
+
+    pub fn mark(&self) -> Option<usize> {
+        self.mark
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
+    pub fn frecency(&self) -> &Frecency {
+        &self.frecency
+    }
+
+    pub fn path(&self) -> &[asana::Resource] {
+        &self.path
+    }
+
+    pub fn is_browsing(&self) -> bool {
+        !self.browse.is_empty()
+    }
+
+    /// Returns whether the search may have more results than the API returned; the listings
+    /// while browsing are complete.
+    pub fn has_more(&self) -> bool {
+        !self.is_browsing() && self.tasks.len() >= asana::SEARCH_LIMIT
+    }
+
+    pub fn new(
+        workspace: Option<&asana::WorkspacesData>,
+        profile: &str,
+        pats: &str,
+        columns: &[columns::Column],
+        frecency: Frecency,
+    ) -> Self {
         State {
-            text: String::new(),
+            columns: columns.to_vec(),
+            sort: asana::Sort::default(),
+            editor: LineEditor::default(),
             tasks: Vec::new(),
-            workspace_gid: workspace_gid.to_string(),
+            workspaces: Vec::new(),
+            workspace_gid: workspace.map(|w| w.gid.clone()).unwrap_or_default(),
+            workspace_name: workspace.map(|w| w.name.clone()).unwrap_or_default(),
+            profile: profile.to_string(),
             pats: pats.to_string(),
             index: 0,
-            checked: HashSet::new(),
+            offset: 0,
+            hscroll: 0,
+            basket: Vec::new(),
+            permalinks: HashMap::new(),
+            failures: Vec::new(),
+            mark: None,
+            error: None,
+            latency: None,
+            my_tasks: false,
+            frecency,
+            browse: Vec::new(),
+            path: Vec::new(),
         }
     }
 
-    pub fn clear_checked(mut self) -> Self {
-        self.checked = HashSet::new();
+    pub fn clear_basket(mut self) -> Self {
+        self.basket = Vec::new();
+        self.mark = None;
+        self
+    }
+
+    pub fn clear_mark(mut self) -> Self {
+        self.mark = None;
         self
     }
 
     pub fn clear_index(mut self) -> Self {
         self.index = 0;
+        self.offset = 0;
+        self.hscroll = 0;
         self
     }
 
     pub fn dec_index(mut self) -> Self {
         self.index -= 1;
+        self.hscroll = 0;
         self
     }
 
     pub fn inc_index(mut self) -> Self {
         self.index += 1;
+        self.hscroll = 0;
         self
     }
 
     pub fn edit_index(mut self, index: usize) -> Self {
+        if self.index != index {
+            self.hscroll = 0;
+        }
         self.index = index;
         self
     }
 
-    pub fn edit_text(mut self, text: &str) -> Self {
-        self.text = text.to_string();
+    pub fn edit_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
         self
     }
 
-    pub async fn search(mut self) -> Result<Self> {
-        let tasks = asana::search_tasks(&self.workspace_gid, &self.text, &self.pats)
-            .await?
-            .data;
-        self.tasks = tasks;
-        Ok(self)
+    /// Scrolls the highlighted title horizontally by `hscroll` grapheme clusters.
+    pub fn edit_hscroll(mut self, hscroll: usize) -> Self {
+        self.hscroll = hscroll;
+        self
+    }
+
+    /// Scrolls the viewport of `rows` lines so that it contains the index.
+    pub fn scroll_into_view(mut self, rows: usize) -> Self {
+        if self.index < self.offset {
+            self.offset = self.index;
+        } else if self.index >= self.offset + rows {
+            self.offset = self.index + 1 - rows;
+        }
+
+        self
+    }
+
+    /// Fits the viewport of `rows` lines to `len` items after the terminal is resized.
+    pub fn fit_viewport(mut self, rows: usize, len: usize) -> Self {
+        self.offset = min(self.offset, len.saturating_sub(rows));
+        self.scroll_into_view(rows)
+    }
+
+    pub fn edit(mut self, f: impl FnOnce(&mut LineEditor)) -> Self {
+        f(&mut self.editor);
+        self
+    }
+
+    pub fn cycle_sort(mut self) -> Self {
+        self.sort = self.sort.next();
+        self
+    }
+
+    pub fn reverse_sort(mut self) -> Self {
+        self.sort = self.sort.reverse();
+        self
+    }
+
+    /// Searches tasks, or the resources of the prefix, by the text; a failure is kept as the error
+    /// instead of the results.
+    pub async fn search(mut self) -> Self {
+        // NOTE: the sort of my tasks does not carry over to the first search
+        if mem::take(&mut self.my_tasks) {
+            self.sort = asana::Sort::default();
+        }
+        let text = self.editor.text();
+        let (resource_type, query) = asana::ResourceType::parse(&text);
+        let start = Instant::now();
+        let res = match resource_type {
+            asana::ResourceType::Task => {
+                asana::search_tasks(&self.workspace_gid, query, self.sort, &self.pats).await
+            }
+            r => asana::typeahead(&self.workspace_gid, r, query, &self.pats).await,
+        };
+        self.latency = Some(start.elapsed());
+        match res {
+            Ok(tasks) => {
+                self.tasks = tasks.data;
+                self.error = None;
+                // NOTE: the resources other than tasks are not sorted by the API
+                if resource_type != asana::ResourceType::Task
+                    || self.sort.by == asana::SortBy::Relevance
+                {
+                    self.rank();
+                }
+            }
+            Err(err) => self.error = Some(err.to_string()),
+        }
+
+        self
+    }
+
+    /// Lists my incomplete tasks as the results before the first search.
+    pub async fn list_my_tasks(mut self) -> Self {
+        self.sort = asana::Sort::MY_TASKS;
+        self.my_tasks = true;
+        self.search_my_tasks(true).await
     }
 
-    pub async fn get_permalink_url(&self) -> Option<String> {
+    /// Searches again in the current sort, or lists my tasks again before the first search.
+    pub async fn resort(self) -> Self {
+        if self.my_tasks {
+            let rank = self.sort.by == asana::SortBy::Relevance;
+            self.search_my_tasks(rank).await
+        } else {
+            self.search().await
+        }
+    }
+
+    /// Lists the teams of the workspace in place of the results to browse the workspace tree.
+    pub async fn browse(mut self) -> Self {
+        let start = Instant::now();
+        let res = asana::get_teams(&self.workspace_gid, &self.pats).await;
+        self.latency = Some(start.elapsed());
+        match res {
+            Ok(teams) => self.push_listing(teams.data),
+            Err(err) => self.error = Some(err.to_string()),
+        }
+
+        self
+    }
+
+    /// Returns whether the row at the index has children to list while browsing; executing
+    /// opens a row without a URL such as a section unless any row is selected.
+    pub fn can_open(&self, execute: bool) -> bool {
         match self.tasks.get(self.index) {
-            Some(t) => t.get_permalink_url(&self.pats).await.ok(),
-            None => None,
+            Some(t) if execute => self.basket.is_empty() && !t.has_url(),
+            Some(t) => !t.is_task(),
+            None => false,
         }
     }
 
-    pub fn get_titles(&self) -> Vec<String> {
-        self.tasks
+    /// Lists the children of the team, project or section at the index while browsing.
+    pub async fn open(mut self) -> Self {
+        let parent = match self.tasks.get(self.index) {
+            Some(t) if !t.is_task() => t.clone(),
+            _ => return self,
+        };
+        let start = Instant::now();
+        let res = parent.get_children(&self.pats).await;
+        self.latency = Some(start.elapsed());
+        match res {
+            Ok(children) => {
+                self.push_listing(children.data);
+                self.path.push(parent);
+            }
+            Err(err) => self.error = Some(err.to_string()),
+        }
+
+        self
+    }
+
+    /// Goes back to the previous listing; the results of the search after the teams.
+    pub fn back(mut self) -> Self {
+        if let Some((tasks, index)) = self.browse.pop() {
+            self.tasks = tasks;
+            self.index = index;
+            self.path.pop();
+        }
+        self.offset = 0;
+        self.hscroll = 0;
+        self.mark = None;
+        self.error = None;
+        self
+    }
+
+    /// Goes back to the results of the search.
+    pub fn leave_browse(mut self) -> Self {
+        self.browse.truncate(1);
+        self.path.clear();
+        self.back()
+    }
+
+    pub async fn list_workspaces(mut self) -> Result<Self> {
+        let workspaces = asana::get_workspaces(&self.pats).await?.data;
+        self.workspaces = workspaces;
+        Ok(self)
+    }
+
+    pub fn select_workspace(mut self) -> Self {
+        if let Some(w) = self.workspaces.get(self.index) {
+            self.workspace_gid = w.gid.clone();
+            self.workspace_name = w.name.clone();
+        }
+
+        self
+    }
+
+    pub fn get_workspace_names(&self) -> Vec<String> {
+        self.workspaces
             .iter()
-            .map(|t| t.name.clone())
+            .map(|w| w.name.clone())
             .collect::<Vec<_>>()
     }
 
+    /// Lays out the columns of `tasks` in `width` columns of the terminal.
+    pub fn layout(&self, tasks: &[asana::Resource], width: usize) -> columns::Layout {
+        columns::Layout::new(&self.columns, tasks, width)
+    }
+
+    pub fn in_basket(&self, task: &asana::Resource) -> bool {
+        self.basket.iter().any(|t| t.gid == task.gid)
+    }
+
     pub fn is_checked(&self, index: &usize) -> bool {
-        self.checked.contains(index)
+        self.tasks
+            .get(*index)
+            .map(|t| self.in_basket(t))
+            .unwrap_or(false)
     }
 
     pub fn check(mut self) -> Self {
-        if self.tasks.len() > self.index {
-            let mut hs = self.checked;
-            hs.insert(self.index);
-            self.checked = hs;
-            return self;
+        if let Some(t) = self.tasks.get(self.index).cloned() {
+            self.add_to_basket(t);
         }
 
         self
     }
 
     pub fn uncheck(mut self) -> Self {
-        let mut hs = self.checked;
-        hs.remove(&self.index);
-        self.checked = hs;
+        if let Some(gid) = self.tasks.get(self.index).map(|t| t.gid.clone()) {
+            self.basket.retain(|t| t.gid != gid);
+        }
+
+        self
+    }
+
+    /// Removes the task at the index from the basket while the basket is listed.
+    pub fn remove_from_basket(mut self) -> Self {
+        if self.index < self.basket.len() {
+            self.basket.remove(self.index);
+        }
+        self.index = min(self.index, self.basket.len().saturating_sub(1));
+        self.hscroll = 0;
         self
     }
 
-    pub async fn get_checked_permalink_urls(&self) -> Vec<String> {
-        let res = future::join_all({
-            self.checked
+    pub fn check_all(mut self) -> Self {
+        for t in self.tasks.clone() {
+            self.add_to_basket(t);
+        }
+
+        self
+    }
+
+    /// Removes the listed tasks from the basket, keeping the tasks selected in other listings.
+    pub fn uncheck_all(mut self) -> Self {
+        let tasks = &self.tasks;
+        self.basket
+            .retain(|b| !tasks.iter().any(|t| t.gid == b.gid));
+        self.mark = None;
+        self
+    }
+
+    pub fn invert_checked(mut self) -> Self {
+        for t in self.tasks.clone() {
+            if self.in_basket(&t) {
+                self.basket.retain(|b| b.gid != t.gid);
+            } else {
+                self.add_to_basket(t);
+            }
+        }
+
+        self
+    }
+
+    /// Marks the start of a range at the index, or checks the range from the mark to the index.
+    pub fn mark_range(mut self) -> Self {
+        match self.mark.take() {
+            Some(mark) => {
+                let (from, to) = (min(mark, self.index), max(mark, self.index));
+                for t in self
+                    .tasks
+                    .iter()
+                    .skip(from)
+                    .take(to + 1 - from)
+                    .cloned()
+                    .collect::<Vec<_>>()
+                {
+                    self.add_to_basket(t);
+                }
+            }
+            None => self.mark = Some(self.index),
+        }
+
+        self
+    }
+
+    /// Returns the tasks in the basket in `order`.
+    pub fn ordered_basket(&self, order: OutputOrder) -> Vec<&asana::Resource> {
+        match order {
+            OutputOrder::Selection => self.basket.iter().collect(),
+            OutputOrder::Screen => {
+                let on_screen = self.tasks.iter().filter(|t| self.in_basket(t));
+                let others = self
+                    .basket
+                    .iter()
+                    .filter(|b| !self.tasks.iter().any(|t| t.gid == b.gid));
+                on_screen.chain(others).collect()
+            }
+        }
+    }
+
+    /// Returns the tasks to output: the basket, or the task at the index if the basket is empty.
+    pub fn targets(&self, order: OutputOrder) -> Vec<&asana::Resource> {
+        if self.basket.is_empty() {
+            self.tasks.get(self.index).into_iter().collect()
+        } else {
+            self.ordered_basket(order)
+        }
+    }
+
+    /// Gets the permalink URLs of the targets except the ones already resolved, and keeps the
+    /// failed tasks with the reasons.
+    pub async fn resolve_permalink_urls(mut self, order: OutputOrder) -> Self {
+        let unresolved = self
+            .targets(order)
+            .into_iter()
+            .filter(|t| !self.permalinks.contains_key(&t.gid))
+            .cloned()
+            .collect::<Vec<_>>();
+        let pats = &self.pats;
+        let res = future::join_all(
+            unresolved
                 .iter()
-                .flat_map(|&i| self.tasks.get(i))
-                .map(|t| async move { t.get_permalink_url(&self.pats).await })
-        })
+                .map(|t| async move { t.get_permalink_url(pats).await }),
+        )
         .await;
 
-        res.iter()
-            .flat_map(|r| r.as_ref().ok())
-            .map(|s| s.to_string())
+        self.failures = Vec::new();
+        for (t, r) in unresolved.into_iter().zip(res) {
+            match r {
+                Ok(url) => {
+                    self.permalinks.insert(t.gid.clone(), url);
+                }
+                Err(err) => self.failures.push((t, err.to_string())),
+            }
+        }
+
+        self
+    }
+
+    /// Records the tasks among the targets whose URLs are resolved in the frecency.
+    pub fn record_targets(mut self, order: OutputOrder) -> Self {
+        let gids = self
+            .targets(order)
+            .iter()
+            .filter(|t| t.is_task() && self.permalinks.contains_key(&t.gid))
+            .map(|t| t.gid.clone())
+            .collect::<Vec<_>>();
+        self.frecency.record(gids.iter().map(|g| g.as_str()));
+        self
+    }
+
+    /// Returns the resolved permalink URLs of the targets in `order`.
+    pub fn get_permalink_urls(&self, order: OutputOrder) -> Vec<String> {
+        self.targets(order)
+            .iter()
+            .flat_map(|t| self.permalinks.get(&t.gid))
+            .cloned()
             .collect::<Vec<_>>()
     }
+
+    /// Moves the frecently used tasks up keeping the order of the others.
+    async fn search_my_tasks(mut self, rank: bool) -> Self {
+        let start = Instant::now();
+        let res = asana::search_my_tasks(&self.workspace_gid, self.sort, &self.pats).await;
+        self.latency = Some(start.elapsed());
+        match res {
+            Ok(tasks) => {
+                self.tasks = tasks.data;
+                self.error = None;
+                if rank {
+                    self.rank();
+                }
+            }
+            Err(err) => self.error = Some(err.to_string()),
+        }
+
+        self
+    }
+
+    fn rank(&mut self) {
+        let frecency = &self.frecency;
+        self.tasks
+            .sort_by_cached_key(|t| Reverse(frecency.score(&t.gid)));
+    }
+
+    fn push_listing(&mut self, tasks: Vec<asana::Resource>) {
+        let tasks = mem::replace(&mut self.tasks, tasks);
+        self.browse.push((tasks, self.index));
+        self.index = 0;
+        self.offset = 0;
+        self.hscroll = 0;
+        self.mark = None;
+        self.error = None;
+    }
+
+    fn add_to_basket(&mut self, task: asana::Resource) {
+        if task.has_url() && !self.in_basket(&task) {
+            self.basket.push(task);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn task(gid: &str) -> asana::Resource {
+        serde_json::from_value(json!({ "gid": gid, "name": gid, "resource_type": "task" })).unwrap()
+    }
+
+    fn gids(tasks: Vec<&asana::Resource>) -> Vec<&str> {
+        tasks.iter().map(|t| t.gid.as_str()).collect()
+    }
+
+    fn state() -> State {
+        let mut state = State::new(None, "default", "pats", &[], Frecency::default());
+        state.tasks = vec![task("a"), task("b"), task("c")];
+        state = state.edit_index(2).check().edit_index(0).check();
+        // NOTE: another search keeps the basket
+        state.tasks = vec![task("d"), task("c")];
+        state.edit_index(0).check()
+    }
+
+    #[test]
+    fn ordered_basket_in_the_order_of_selection() {
+        let state = state();
+        assert_eq!(
+            gids(state.ordered_basket(OutputOrder::Selection)),
+            ["c", "a", "d"]
+        );
+    }
+
+    #[test]
+    fn ordered_basket_in_the_order_on_screen() {
+        let state = state();
+        assert_eq!(
+            gids(state.ordered_basket(OutputOrder::Screen)),
+            ["d", "c", "a"]
+        );
+    }
+
+    #[test]
+    fn targets_the_task_at_the_index_without_selection() {
+        let mut state = State::new(None, "default", "pats", &[], Frecency::default());
+        state.tasks = vec![task("a"), task("b")];
+        let state = state.edit_index(1);
+        assert_eq!(gids(state.targets(OutputOrder::Selection)), ["b"]);
+    }
 }
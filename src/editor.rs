@@ -0,0 +1,301 @@
+use std::cell::Cell;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const UNDO_LIMIT: usize = 100;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Insert,
+    Other,
+}
+
+/// Line editor of the prompt; the cursor is an index of grapheme clusters.
+#[derive(Default)]
+pub(crate) struct LineEditor {
+    graphemes: Vec<String>,
+    cursor: usize,
+    kill_ring: Vec<String>,
+    undo: Vec<(Vec<String>, usize)>,
+    last_edit: Option<Edit>,
+    // NOTE: the first visible grapheme cluster when the text is wider than the prompt
+    scroll: Cell<usize>,
+}
+
+impl LineEditor {
+    pub fn text(&self) -> String {
+        self.graphemes.concat()
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.save(Edit::Insert);
+        let mut head = self.graphemes[..self.cursor].concat();
+        head.push(c);
+        let tail = self.graphemes[self.cursor..].concat();
+        // NOTE: re-segment so that combining characters and ZWJ sequences join the cluster
+        self.cursor = split(&head).len();
+        self.graphemes = split(&(head + &tail));
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+        self.last_edit = None;
+    }
+
+    pub fn move_right(&mut self) {
+        if self.cursor < self.graphemes.len() {
+            self.cursor += 1;
+        }
+        self.last_edit = None;
+    }
+
+    pub fn move_head(&mut self) {
+        self.cursor = 0;
+        self.last_edit = None;
+    }
+
+    pub fn move_tail(&mut self) {
+        self.cursor = self.graphemes.len();
+        self.last_edit = None;
+    }
+
+    pub fn move_word_left(&mut self) {
+        self.cursor = self.word_left();
+        self.last_edit = None;
+    }
+
+    pub fn move_word_right(&mut self) {
+        self.cursor = self.word_right();
+        self.last_edit = None;
+    }
+
+    pub fn delete_backward(&mut self) {
+        if self.cursor > 0 {
+            self.save(Edit::Other);
+            self.cursor -= 1;
+            self.graphemes.remove(self.cursor);
+        }
+    }
+
+    pub fn delete_forward(&mut self) {
+        if self.cursor < self.graphemes.len() {
+            self.save(Edit::Other);
+            self.graphemes.remove(self.cursor);
+        }
+    }
+
+    pub fn kill_line(&mut self) {
+        self.kill(self.cursor, self.graphemes.len());
+    }
+
+    pub fn kill_line_backward(&mut self) {
+        self.kill(0, self.cursor);
+    }
+
+    pub fn kill_word_backward(&mut self) {
+        self.kill(self.word_left(), self.cursor);
+    }
+
+    pub fn kill_word_forward(&mut self) {
+        self.kill(self.cursor, self.word_right());
+    }
+
+    pub fn yank(&mut self) {
+        if let Some(s) = self.kill_ring.last().cloned() {
+            self.save(Edit::Other);
+            let head = self.graphemes[..self.cursor].concat() + &s;
+            let tail = self.graphemes[self.cursor..].concat();
+            self.cursor = split(&head).len();
+            self.graphemes = split(&(head + &tail));
+        }
+    }
+
+    pub fn undo(&mut self) {
+        if let Some((graphemes, cursor)) = self.undo.pop() {
+            self.graphemes = graphemes;
+            self.cursor = cursor;
+        }
+        self.last_edit = None;
+    }
+
+    /// Returns the visible text within `width` columns and the cursor column in it.
+    pub fn view(&self, width: usize) -> (String, usize) {
+        let widths = self.graphemes.iter().map(|g| g.width()).collect::<Vec<_>>();
+        let mut scroll = if widths.iter().sum::<usize>() < width {
+            0
+        } else {
+            self.scroll.get().min(self.cursor)
+        };
+        while scroll < self.cursor && widths[scroll..self.cursor].iter().sum::<usize>() >= width {
+            scroll += 1;
+        }
+        self.scroll.set(scroll);
+
+        let mut col = 0;
+        let mut s = String::new();
+        for (g, w) in self.graphemes.iter().zip(widths.iter()).skip(scroll) {
+            if col + w > width {
+                break;
+            }
+            s.push_str(g);
+            col += w;
+        }
+
+        (s, widths[scroll..self.cursor].iter().sum())
+    }
+
+    fn kill(&mut self, from: usize, to: usize) {
+        if from < to {
+            self.save(Edit::Other);
+            let killed = self.graphemes.drain(from..to).collect::<String>();
+            self.kill_ring.push(killed);
+            self.cursor = from;
+        }
+    }
+
+    fn save(&mut self, edit: Edit) {
+        // NOTE: consecutive insertions are undone at once
+        if edit == Edit::Insert && self.last_edit == Some(Edit::Insert) {
+            return;
+        }
+
+        self.undo.push((self.graphemes.clone(), self.cursor));
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.last_edit = Some(edit);
+    }
+
+    fn word_left(&self) -> usize {
+        let mut i = self.cursor;
+        while i > 0 && !is_word(&self.graphemes[i - 1]) {
+            i -= 1;
+        }
+        while i > 0 && is_word(&self.graphemes[i - 1]) {
+            i -= 1;
+        }
+
+        i
+    }
+
+    fn word_right(&self) -> usize {
+        let mut i = self.cursor;
+        while i < self.graphemes.len() && !is_word(&self.graphemes[i]) {
+            i += 1;
+        }
+        while i < self.graphemes.len() && is_word(&self.graphemes[i]) {
+            i += 1;
+        }
+
+        i
+    }
+}
+
+fn split(s: &str) -> Vec<String> {
+    s.graphemes(true).map(|g| g.to_string()).collect()
+}
+
+fn is_word(g: &str) -> bool {
+    g.chars().any(|c| c.is_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(s: &str) -> LineEditor {
+        let mut e = LineEditor::default();
+        s.chars().for_each(|c| e.insert(c));
+        e
+    }
+
+    #[test]
+    fn insert_joins_combining_characters_and_zwj_sequences() {
+        let e = editor("e\u{301}👨\u{200d}👩\u{200d}👧");
+        assert_eq!(e.graphemes, vec!["e\u{301}", "👨\u{200d}👩\u{200d}👧"]);
+        assert_eq!(e.cursor, 2);
+    }
+
+    #[test]
+    fn insert_in_the_middle() {
+        let mut e = editor("ac");
+        e.move_left();
+        e.insert('b');
+        assert_eq!(e.text(), "abc");
+        assert_eq!(e.cursor, 2);
+    }
+
+    #[test]
+    fn kill_word_backward_and_yank() {
+        let mut e = editor("foo bar  ");
+        e.kill_word_backward();
+        assert_eq!(e.text(), "foo ");
+        e.move_head();
+        e.yank();
+        assert_eq!(e.text(), "bar  foo ");
+        assert_eq!(e.cursor, 5);
+    }
+
+    #[test]
+    fn kill_word_forward() {
+        let mut e = editor("foo, bar");
+        e.move_head();
+        e.kill_word_forward();
+        assert_eq!(e.text(), ", bar");
+        e.kill_word_forward();
+        assert_eq!(e.text(), "");
+        e.yank();
+        assert_eq!(e.text(), ", bar");
+    }
+
+    #[test]
+    fn undo_consecutive_insertions_at_once() {
+        let mut e = editor("foo");
+        e.delete_backward();
+        e.insert('x');
+        e.insert('y');
+        assert_eq!(e.text(), "foxy");
+        e.undo();
+        assert_eq!(e.text(), "fo");
+        e.undo();
+        assert_eq!(e.text(), "foo");
+        e.undo();
+        assert_eq!(e.text(), "");
+        assert_eq!(e.cursor, 0);
+    }
+
+    #[test]
+    fn undo_after_moving_the_cursor() {
+        let mut e = editor("ab");
+        e.move_head();
+        e.insert('x');
+        assert_eq!(e.text(), "xab");
+        e.undo();
+        assert_eq!(e.text(), "ab");
+    }
+
+    #[test]
+    fn view_fits_the_text() {
+        let e = editor("abc");
+        assert_eq!(e.view(10), ("abc".to_string(), 3));
+    }
+
+    #[test]
+    fn view_scrolls_to_the_cursor() {
+        let mut e = editor("abcdef");
+        assert_eq!(e.view(4), ("def".to_string(), 3));
+        e.move_head();
+        assert_eq!(e.view(4), ("abcd".to_string(), 0));
+    }
+
+    #[test]
+    fn view_does_not_split_wide_characters() {
+        let mut e = editor("日本語");
+        e.move_head();
+        assert_eq!(e.view(3), ("日".to_string(), 0));
+        e.move_tail();
+        assert_eq!(e.view(3), ("語".to_string(), 2));
+        assert_eq!(e.view(0), ("".to_string(), 0));
+    }
+}
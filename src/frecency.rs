@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+const FRECENCY_FILE: &str = "frecency.toml";
+const MAX_VISITS: usize = 10;
+const MAX_TASKS: usize = 1000;
+const DAY: i64 = 24 * 60 * 60;
+// NOTE: the weight of a visit by its age in days, as in the frecency of Firefox
+const WEIGHTS: [(i64, u64); 4] = [(4, 100), (14, 70), (31, 50), (90, 30)];
+const OLD_WEIGHT: u64 = 10;
+
+/// Timestamps of the tasks whose URLs were output, to rank frequently and recently used tasks.
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub(crate) struct Frecency {
+    #[serde(default)]
+    tasks: BTreeMap<String, Vec<i64>>,
+}
+
+impl Frecency {
+    pub fn load() -> Result<Self> {
+        config::read_toml(&path()?)
+    }
+
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = path()?;
+        config::write_private(&path, &toml::to_string(self)?)?;
+
+        Ok(path)
+    }
+
+    /// Records a visit of each task now, keeping the latest visits of the latest tasks.
+    pub fn record<'a>(&mut self, gids: impl Iterator<Item = &'a str>) {
+        let now = now();
+        for gid in gids {
+            let visits = self.tasks.entry(gid.to_string()).or_default();
+            visits.push(now);
+            if visits.len() > MAX_VISITS {
+                visits.remove(0);
+            }
+        }
+
+        while self.tasks.len() > MAX_TASKS {
+            let oldest = self
+                .tasks
+                .iter()
+                .min_by_key(|(_, visits)| visits.last().copied().unwrap_or(0))
+                .map(|(gid, _)| gid.clone());
+            if let Some(gid) = oldest {
+                self.tasks.remove(&gid);
+            }
+        }
+    }
+
+    /// Returns the score of the task; 0 if it has never been visited.
+    pub fn score(&self, gid: &str) -> u64 {
+        let now = now();
+        self.tasks
+            .get(gid)
+            .map(|visits| {
+                visits
+                    .iter()
+                    .map(|&t| {
+                        let days = (now - t) / DAY;
+                        WEIGHTS
+                            .iter()
+                            .find(|(d, _)| days < *d)
+                            .map(|(_, w)| *w)
+                            .unwrap_or(OLD_WEIGHT)
+                    })
+                    .sum()
+            })
+            .unwrap_or(0)
+    }
+
+    /// Forgets the tasks and returns the number of the tasks forgotten.
+    pub fn forget(&mut self, gids: &[&str]) -> usize {
+        let len = self.tasks.len();
+        self.tasks.retain(|gid, _| !gids.contains(&gid.as_str()));
+        len - self.tasks.len()
+    }
+
+    /// Forgets all the tasks and returns the number of the tasks forgotten.
+    pub fn forget_all(&mut self) -> usize {
+        let len = self.tasks.len();
+        self.tasks.clear();
+        len
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn path() -> Result<PathBuf> {
+    config::dir().map(|dir| dir.join(FRECENCY_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn visited(days: &[i64]) -> Frecency {
+        let now = now();
+        let visits = days.iter().map(|d| now - d * DAY).collect();
+        Frecency {
+            tasks: BTreeMap::from([("1".to_string(), visits)]),
+        }
+    }
+
+    #[test]
+    fn score_weighs_visits_by_age() {
+        assert_eq!(visited(&[0]).score("1"), 100);
+        assert_eq!(visited(&[10]).score("1"), 70);
+        assert_eq!(visited(&[20]).score("1"), 50);
+        assert_eq!(visited(&[60]).score("1"), 30);
+        assert_eq!(visited(&[365]).score("1"), 10);
+        assert_eq!(visited(&[365, 10, 0]).score("1"), 180);
+    }
+
+    #[test]
+    fn score_of_an_unvisited_task_is_zero() {
+        assert_eq!(visited(&[0]).score("2"), 0);
+    }
+
+    #[test]
+    fn record_keeps_the_latest_visits() {
+        let mut frecency = Frecency::default();
+        for _ in 0..MAX_VISITS + 5 {
+            frecency.record(["1"].into_iter());
+        }
+        assert_eq!(frecency.tasks["1"].len(), MAX_VISITS);
+        assert_eq!(frecency.score("1"), 100 * MAX_VISITS as u64);
+    }
+
+    #[test]
+    fn forget_the_tasks() {
+        let mut frecency = Frecency::default();
+        frecency.record(["1", "2", "3"].into_iter());
+        assert_eq!(frecency.forget(&["1", "4"]), 1);
+        assert_eq!(frecency.score("1"), 0);
+        assert_eq!(frecency.forget_all(), 2);
+        assert_eq!(frecency.score("2"), 0);
+    }
+}
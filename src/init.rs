@@ -0,0 +1,61 @@
+use std::io::{stdin, stdout, Write};
+
+use anyhow::{anyhow, Result};
+use termion::input::TermRead;
+
+use crate::asana;
+use crate::config;
+
+pub(crate) async fn run(profile: &str) -> Result<()> {
+    let mut config = config::Config::load()?;
+    let mut stdout = stdout();
+
+    write!(stdout, "Personal Access Tokens (PATs): ")?;
+    stdout.flush()?;
+    let pats = stdin()
+        .read_passwd(&mut stdout)?
+        .filter(|s| !s.is_empty())
+        .ok_or(anyhow!("Failed to read pats"))?;
+    writeln!(stdout)?;
+
+    let me = asana::get_me(&pats)
+        .await
+        .map_err(|_| anyhow!("Failed to validate pats"))?
+        .data;
+    writeln!(stdout, "Authenticated as {} <{}>", me.name, me.email)?;
+
+    let workspaces = asana::get_workspaces(&pats).await?.data;
+    if workspaces.is_empty() {
+        return Err(anyhow!("Failed to find accessible workspaces"));
+    }
+    workspaces
+        .iter()
+        .enumerate()
+        .try_for_each(|(i, w)| writeln!(stdout, "{:>3}: {} ({})", i + 1, w.name, w.gid))?;
+    let workspace = loop {
+        write!(stdout, "Select a workspace [1-{}]: ", workspaces.len())?;
+        stdout.flush()?;
+        let line = TermRead::read_line(&mut stdin())?.ok_or(anyhow!("Failed to read workspace"))?;
+        match line.trim().parse::<usize>() {
+            Ok(n) if n >= 1 && n <= workspaces.len() => break &workspaces[n - 1],
+            _ => continue,
+        }
+    };
+
+    config.profiles.insert(
+        profile.to_string(),
+        config::Profile {
+            pats: Some(pats),
+            workspace_gid: Some(workspace.gid.clone()),
+        },
+    );
+    let path = config.save_profile(profile)?;
+    writeln!(
+        stdout,
+        "Saved profile \"{}\" to {}",
+        profile,
+        path.display()
+    )?;
+
+    Ok(())
+}
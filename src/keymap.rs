@@ -0,0 +1,528 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use termion::event::Key;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Action {
+    Exit,
+    Search,
+    Execute,
+    Select,
+    Redisplay,
+    FocusPrompt,
+    InsertMode,
+    NormalMode,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    MoveTop,
+    MoveBottom,
+    MoveHead,
+    MoveTail,
+    MoveWordLeft,
+    MoveWordRight,
+    DeleteBackward,
+    DeleteForward,
+    KillLine,
+    KillLineBackward,
+    KillWordBackward,
+    KillWordForward,
+    Yank,
+    Undo,
+    CycleSort,
+    ReverseSort,
+    SelectAll,
+    DeselectAll,
+    InvertSelection,
+    MarkRange,
+    ShowBasket,
+    Retry,
+    Browse,
+    Help,
+    Unbind,
+}
+
+impl Action {
+    pub fn description(&self) -> &str {
+        match self {
+            Action::Exit => "Exit",
+            Action::Search => "Search tasks",
+            Action::Execute => "Output the URLs of the selected tasks",
+            Action::Select => "Select or deselect the task",
+            Action::Redisplay => "Redisplay the terminal",
+            Action::FocusPrompt => "Move to the prompt",
+            Action::InsertMode => "Switch to insert mode",
+            Action::NormalMode => "Switch to normal mode",
+            Action::MoveLeft => "Move the cursor left or scroll the title left",
+            Action::MoveRight => "Move the cursor right or scroll the title right",
+            Action::MoveUp => "Move the cursor up",
+            Action::MoveDown => "Move the cursor down",
+            Action::MoveTop => "Move the cursor to the top",
+            Action::MoveBottom => "Move the cursor to the bottom",
+            Action::MoveHead => "Move the cursor to the beginning of the line",
+            Action::MoveTail => "Move the cursor to the end of the line",
+            Action::MoveWordLeft => "Move the cursor a word left",
+            Action::MoveWordRight => "Move the cursor a word right",
+            Action::DeleteBackward => "Delete the character before the cursor",
+            Action::DeleteForward => "Delete the character under the cursor",
+            Action::KillLine => "Delete to the end of the line",
+            Action::KillLineBackward => "Delete to the beginning of the line",
+            Action::KillWordBackward => "Delete the word before the cursor",
+            Action::KillWordForward => "Delete the word after the cursor",
+            Action::Yank => "Paste the last deleted text",
+            Action::Undo => "Undo the last edit",
+            Action::CycleSort => "Cycle the sort and search again",
+            Action::ReverseSort => "Reverse the sort order and search again",
+            Action::SelectAll => "Select all tasks",
+            Action::DeselectAll => "Deselect all tasks",
+            Action::InvertSelection => "Invert the selection",
+            Action::MarkRange => "Mark the start of a range, or select the range",
+            Action::ShowBasket => "Show or hide the selected tasks",
+            Action::Retry => "Retry getting the URLs that failed",
+            Action::Browse => "Browse teams, projects and sections",
+            Action::Help => "Show the key bindings",
+            Action::Unbind => "Remove the binding",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Preset {
+    #[default]
+    Emacs,
+    Vi,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub(crate) struct KeysConfig {
+    #[serde(default)]
+    pub preset: Preset,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prompt: BTreeMap<String, Action>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub normal: BTreeMap<String, Action>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub results: BTreeMap<String, Action>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Context {
+    Prompt,
+    Normal,
+    Results,
+}
+
+pub(crate) enum Lookup {
+    Action(Action),
+    Prefix,
+    Unbound,
+}
+
+type Bindings = Vec<(Vec<Key>, Action)>;
+
+pub(crate) struct Keymap {
+    prompt: Bindings,
+    normal: Bindings,
+    results: Bindings,
+}
+
+impl Keymap {
+    pub fn new(config: &KeysConfig) -> Result<Self> {
+        let mut keymap = match config.preset {
+            Preset::Emacs => emacs(),
+            Preset::Vi => vi(),
+        };
+        for (context, bindings) in [
+            (Context::Prompt, &config.prompt),
+            (Context::Normal, &config.normal),
+            (Context::Results, &config.results),
+        ] {
+            for (keys, &action) in bindings {
+                keymap.bind(context, parse_keys(keys)?, action);
+            }
+        }
+
+        Ok(keymap)
+    }
+
+    pub fn lookup(&self, context: Context, keys: &[Key]) -> Lookup {
+        let bindings = self.bindings(context);
+        if let Some((_, action)) = bindings.iter().find(|(k, _)| k == keys) {
+            return Lookup::Action(*action);
+        }
+
+        if bindings
+            .iter()
+            .any(|(k, _)| k.len() > keys.len() && k.starts_with(keys))
+        {
+            return Lookup::Prefix;
+        }
+
+        Lookup::Unbound
+    }
+
+    /// Returns the keys bound in `context` grouped by action in the order of the bindings.
+    pub fn describe(&self, context: Context) -> Vec<(Vec<String>, Action)> {
+        let mut actions: Vec<(Vec<String>, Action)> = Vec::new();
+        for (keys, action) in self.bindings(context) {
+            let keys = format_keys(keys);
+            match actions.iter_mut().find(|(_, a)| a == action) {
+                Some((k, _)) => k.push(keys),
+                None => actions.push((vec![keys], *action)),
+            }
+        }
+
+        actions
+    }
+
+    fn bindings(&self, context: Context) -> &Bindings {
+        match context {
+            Context::Prompt => &self.prompt,
+            Context::Normal => &self.normal,
+            Context::Results => &self.results,
+        }
+    }
+
+    fn bind(&mut self, context: Context, keys: Vec<Key>, action: Action) {
+        let bindings = match context {
+            Context::Prompt => &mut self.prompt,
+            Context::Normal => &mut self.normal,
+            Context::Results => &mut self.results,
+        };
+        bindings.retain(|(k, _)| k != &keys);
+        if action != Action::Unbind {
+            bindings.push((keys, action));
+        }
+    }
+}
+
+fn emacs() -> Keymap {
+    Keymap {
+        prompt: build(&[
+            ("C-c", Action::Exit),
+            ("Enter", Action::Search),
+            ("Left", Action::MoveLeft),
+            ("C-b", Action::MoveLeft),
+            ("Right", Action::MoveRight),
+            ("C-f", Action::MoveRight),
+            ("M-b", Action::MoveWordLeft),
+            ("M-f", Action::MoveWordRight),
+            ("Backspace", Action::DeleteBackward),
+            ("C-h", Action::DeleteBackward),
+            ("Delete", Action::DeleteForward),
+            ("C-d", Action::DeleteForward),
+            ("Home", Action::MoveHead),
+            ("C-a", Action::MoveHead),
+            ("End", Action::MoveTail),
+            ("C-e", Action::MoveTail),
+            ("C-k", Action::KillLine),
+            ("C-u", Action::KillLineBackward),
+            ("C-w", Action::KillWordBackward),
+            ("M-d", Action::KillWordForward),
+            ("C-y", Action::Yank),
+            ("C-_", Action::Undo),
+            ("C-z", Action::Undo),
+            ("F2", Action::ShowBasket),
+            ("Down", Action::MoveDown),
+            ("C-n", Action::MoveDown),
+            ("F3", Action::Browse),
+            ("F1", Action::Help),
+            ("C-g", Action::Redisplay),
+        ]),
+        normal: Vec::new(),
+        results: build(&[
+            ("C-c", Action::Exit),
+            ("C-s", Action::FocusPrompt),
+            ("Up", Action::MoveUp),
+            ("C-p", Action::MoveUp),
+            ("PageUp", Action::MoveTop),
+            ("C-v", Action::MoveTop),
+            ("Down", Action::MoveDown),
+            ("C-n", Action::MoveDown),
+            ("PageDown", Action::MoveBottom),
+            ("M-v", Action::MoveBottom),
+            ("Left", Action::MoveLeft),
+            ("Right", Action::MoveRight),
+            ("C-o", Action::CycleSort),
+            ("C-r", Action::ReverseSort),
+            ("Enter", Action::Execute),
+            ("Tab", Action::Select),
+            ("M-a", Action::SelectAll),
+            ("M-u", Action::DeselectAll),
+            ("M-i", Action::InvertSelection),
+            ("M-m", Action::MarkRange),
+            ("M-b", Action::ShowBasket),
+            ("F2", Action::ShowBasket),
+            ("r", Action::Retry),
+            ("F3", Action::Browse),
+            ("F1", Action::Help),
+            ("?", Action::Help),
+            ("C-g", Action::Redisplay),
+        ]),
+    }
+}
+
+fn vi() -> Keymap {
+    Keymap {
+        prompt: build(&[
+            ("C-c", Action::Exit),
+            ("Enter", Action::Search),
+            ("Esc", Action::NormalMode),
+            ("Left", Action::MoveLeft),
+            ("Right", Action::MoveRight),
+            ("Backspace", Action::DeleteBackward),
+            ("C-h", Action::DeleteBackward),
+            ("Delete", Action::DeleteForward),
+            ("C-w", Action::KillWordBackward),
+            ("C-u", Action::KillLineBackward),
+            ("F2", Action::ShowBasket),
+            ("Down", Action::MoveDown),
+            ("F3", Action::Browse),
+            ("F1", Action::Help),
+            ("C-g", Action::Redisplay),
+        ]),
+        normal: build(&[
+            ("C-c", Action::Exit),
+            ("q", Action::Exit),
+            ("Enter", Action::Search),
+            ("i", Action::InsertMode),
+            ("h", Action::MoveLeft),
+            ("Left", Action::MoveLeft),
+            ("l", Action::MoveRight),
+            ("Right", Action::MoveRight),
+            ("b", Action::MoveWordLeft),
+            ("e", Action::MoveWordRight),
+            ("0", Action::MoveHead),
+            ("$", Action::MoveTail),
+            ("x", Action::DeleteForward),
+            ("Delete", Action::DeleteForward),
+            ("D", Action::KillLine),
+            ("p", Action::Yank),
+            ("u", Action::Undo),
+            ("F2", Action::ShowBasket),
+            ("j", Action::MoveDown),
+            ("Down", Action::MoveDown),
+            ("F3", Action::Browse),
+            ("F1", Action::Help),
+            ("?", Action::Help),
+            ("C-g", Action::Redisplay),
+        ]),
+        results: build(&[
+            ("C-c", Action::Exit),
+            ("q", Action::Exit),
+            ("i", Action::FocusPrompt),
+            ("/", Action::FocusPrompt),
+            ("k", Action::MoveUp),
+            ("Up", Action::MoveUp),
+            ("j", Action::MoveDown),
+            ("Down", Action::MoveDown),
+            ("g g", Action::MoveTop),
+            ("G", Action::MoveBottom),
+            ("h", Action::MoveLeft),
+            ("Left", Action::MoveLeft),
+            ("l", Action::MoveRight),
+            ("Right", Action::MoveRight),
+            ("s", Action::CycleSort),
+            ("S", Action::ReverseSort),
+            ("Enter", Action::Execute),
+            ("Tab", Action::Select),
+            ("Space", Action::Select),
+            ("A", Action::SelectAll),
+            ("U", Action::DeselectAll),
+            ("I", Action::InvertSelection),
+            ("V", Action::MarkRange),
+            ("b", Action::ShowBasket),
+            ("F2", Action::ShowBasket),
+            ("r", Action::Retry),
+            ("F3", Action::Browse),
+            ("F1", Action::Help),
+            ("?", Action::Help),
+            ("C-g", Action::Redisplay),
+        ]),
+    }
+}
+
+fn build(bindings: &[(&str, Action)]) -> Bindings {
+    bindings
+        .iter()
+        .map(|(keys, action)| (parse_keys(keys).expect("invalid preset"), *action))
+        .collect()
+}
+
+/// Parses space-separated key sequence such as `C-x`, `M-v`, `g g` or `PageDown`.
+fn parse_keys(s: &str) -> Result<Vec<Key>> {
+    let keys = s
+        .split_whitespace()
+        .map(parse_key)
+        .collect::<Result<Vec<_>>>()?;
+    if keys.is_empty() {
+        return Err(anyhow!("Failed to parse key \"{}\"", s));
+    }
+
+    Ok(keys)
+}
+
+/// Formats a key sequence in the same notation as `parse_keys`.
+fn format_keys(keys: &[Key]) -> String {
+    keys.iter().map(format_key).collect::<Vec<_>>().join(" ")
+}
+
+fn format_key(key: &Key) -> String {
+    match key {
+        Key::Char('\n') => "Enter".to_string(),
+        Key::Char('\t') => "Tab".to_string(),
+        Key::Char(' ') => "Space".to_string(),
+        Key::Backspace => "Backspace".to_string(),
+        Key::Delete => "Delete".to_string(),
+        Key::Esc => "Esc".to_string(),
+        Key::Left => "Left".to_string(),
+        Key::Right => "Right".to_string(),
+        Key::Up => "Up".to_string(),
+        Key::Down => "Down".to_string(),
+        Key::Home => "Home".to_string(),
+        Key::End => "End".to_string(),
+        Key::PageUp => "PageUp".to_string(),
+        Key::PageDown => "PageDown".to_string(),
+        Key::BackTab => "BackTab".to_string(),
+        Key::Ctrl('7') => "C-_".to_string(),
+        Key::Ctrl(c) => format!("C-{}", c),
+        Key::Alt(c) => format!("M-{}", c),
+        Key::F(n) => format!("F{}", n),
+        Key::Char(c) => c.to_string(),
+        _ => format!("{:?}", key),
+    }
+}
+
+fn parse_key(s: &str) -> Result<Key> {
+    let key = match s {
+        "Enter" => Key::Char('\n'),
+        "Tab" => Key::Char('\t'),
+        "Space" => Key::Char(' '),
+        "Backspace" => Key::Backspace,
+        "Delete" => Key::Delete,
+        "Esc" => Key::Esc,
+        "Left" => Key::Left,
+        "Right" => Key::Right,
+        "Up" => Key::Up,
+        "Down" => Key::Down,
+        "Home" => Key::Home,
+        "End" => Key::End,
+        "PageUp" => Key::PageUp,
+        "PageDown" => Key::PageDown,
+        "BackTab" => Key::BackTab,
+        // NOTE: termion reads 0x1f (Ctrl-_ or Ctrl-/ on most terminals) as Ctrl-7
+        "C-_" | "C-/" => Key::Ctrl('7'),
+        _ => {
+            let single = |t: &str| {
+                let mut chars = t.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Some(c),
+                    _ => None,
+                }
+            };
+            if let Some(c) = s.strip_prefix("C-").and_then(single) {
+                Key::Ctrl(c)
+            } else if let Some(c) = s.strip_prefix("M-").and_then(single) {
+                Key::Alt(c)
+            } else if let Some(n) = s.strip_prefix('F').and_then(|n| n.parse::<u8>().ok()) {
+                Key::F(n)
+            } else if let Some(c) = single(s) {
+                Key::Char(c)
+            } else {
+                return Err(anyhow!("Failed to parse key \"{}\"", s));
+            }
+        }
+    };
+
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_keys_of_a_sequence() {
+        assert_eq!(
+            parse_keys("C-x M-v g  PageDown").unwrap(),
+            vec![Key::Ctrl('x'), Key::Alt('v'), Key::Char('g'), Key::PageDown]
+        );
+        assert_eq!(parse_keys("C-/").unwrap(), vec![Key::Ctrl('7')]);
+        assert_eq!(parse_keys("F12").unwrap(), vec![Key::F(12)]);
+        assert_eq!(parse_keys("Space").unwrap(), vec![Key::Char(' ')]);
+    }
+
+    #[test]
+    fn parse_keys_fails_on_unknown_keys() {
+        assert!(parse_keys("").is_err());
+        assert!(parse_keys("C-").is_err());
+        assert!(parse_keys("C-xy").is_err());
+        assert!(parse_keys("Foo").is_err());
+    }
+
+    #[test]
+    fn lookup_a_prefix_of_a_sequence() {
+        let keymap = Keymap::new(&KeysConfig {
+            preset: Preset::Vi,
+            ..Default::default()
+        })
+        .unwrap();
+        let g = Key::Char('g');
+        assert!(matches!(
+            keymap.lookup(Context::Results, &[g]),
+            Lookup::Prefix
+        ));
+        assert!(matches!(
+            keymap.lookup(Context::Results, &[g, g]),
+            Lookup::Action(Action::MoveTop)
+        ));
+        assert!(matches!(
+            keymap.lookup(Context::Results, &[g, Key::Char('x')]),
+            Lookup::Unbound
+        ));
+        assert!(matches!(
+            keymap.lookup(Context::Results, &[Key::Char('G')]),
+            Lookup::Action(Action::MoveBottom)
+        ));
+    }
+
+    #[test]
+    fn config_overrides_and_unbinds_the_preset() {
+        let mut config = KeysConfig::default();
+        config.results.insert("C-j".to_string(), Action::MoveDown);
+        config.results.insert("C-c".to_string(), Action::Unbind);
+        let keymap = Keymap::new(&config).unwrap();
+        assert!(matches!(
+            keymap.lookup(Context::Results, &[Key::Ctrl('j')]),
+            Lookup::Action(Action::MoveDown)
+        ));
+        assert!(matches!(
+            keymap.lookup(Context::Results, &[Key::Ctrl('c')]),
+            Lookup::Unbound
+        ));
+        assert!(matches!(
+            keymap.lookup(Context::Prompt, &[Key::Ctrl('c')]),
+            Lookup::Action(Action::Exit)
+        ));
+    }
+
+    #[test]
+    fn format_keys_round_trips() {
+        for s in ["C-_", "M-d", "g g", "Enter", "Tab", "F1", "?"] {
+            assert_eq!(format_keys(&parse_keys(s).unwrap()), s);
+        }
+    }
+
+    #[test]
+    fn describe_groups_keys_by_action() {
+        let keymap = Keymap::new(&KeysConfig::default()).unwrap();
+        let actions = keymap.describe(Context::Prompt);
+        let (keys, _) = actions.iter().find(|(_, a)| *a == Action::Undo).unwrap();
+        assert_eq!(keys, &["C-_", "C-z"]);
+    }
+}
@@ -4,25 +4,104 @@ use std::process;
 
 mod asana;
 mod cli;
+mod columns;
+mod config;
 mod controller;
+mod doctor;
+mod editor;
+mod frecency;
+mod init;
+mod keymap;
+mod render;
 mod terminal;
+mod theme;
 
 #[tokio::main]
 async fn main() {
     let matches = cli::build().get_matches();
-    let workspace_gid = matches
-        .value_of(cli::WORKSPACE_GID)
-        .expect("Error: Failed to specify workspace_gid");
+    let profile_name = matches
+        .value_of(cli::PROFILE)
+        .expect("Error: Failed to specify profile");
+    if matches.subcommand_matches(cli::INIT).is_some() {
+        init::run(profile_name).await.unwrap_or_else(|err| {
+            eprintln!("Error: {}", err);
+            process::exit(1);
+        });
+        return;
+    }
+
+    // NOTE: diagnose before loading the config file, which may be broken
+    if let Some(m) = matches.subcommand_matches(cli::DOCTOR) {
+        let workspace_gid = m.value_of(cli::WORKSPACE_GID);
+        let pats = matches.value_of(cli::PATS);
+        if !doctor::run(profile_name, workspace_gid, pats).await {
+            process::exit(1);
+        }
+        return;
+    }
+
+    let config = config::Config::load().unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    });
+    let profile = config.profile(profile_name);
+
+    let mut frecency = frecency::Frecency::load().unwrap_or_else(|err| {
+        eprintln!("Warning: {}", err);
+        frecency::Frecency::default()
+    });
+    if let Some(m) = matches.subcommand_matches(cli::FORGET) {
+        let n = if m.is_present(cli::ALL) {
+            frecency.forget_all()
+        } else {
+            let gids = m.values_of(cli::GIDS).map(|v| v.collect::<Vec<_>>());
+            frecency.forget(&gids.unwrap_or_default())
+        };
+        frecency.save().unwrap_or_else(|err| {
+            eprintln!("Error: {}", err);
+            process::exit(1);
+        });
+        println!("Forgot {} task(s)", n);
+        return;
+    }
+
+    let (positional_workspace_gid, positional_pats, file) = cli::positionals(&matches);
     let pats = matches
         .value_of(cli::PATS)
-        .expect("Error: Failed to specify pats");
-    let file = matches.value_of(cli::FILE);
-    match asana::get_workspace(workspace_gid, pats).await {
-        Ok(false) | Err(_) => {
-            eprintln!("Error: Failed to access workspace({})", workspace_gid);
+        .or(positional_pats)
+        .or(profile.pats.as_deref())
+        .unwrap_or_else(|| {
+            eprintln!("Error: Failed to specify pats, try `asaru init`");
             process::exit(1);
-        }
-        _ => {}
+        });
+    if matches.subcommand_matches(cli::WORKSPACES).is_some() {
+        let workspaces = asana::get_workspaces(pats).await.unwrap_or_else(|_| {
+            eprintln!("Error: Failed to get workspaces");
+            process::exit(1);
+        });
+        workspaces
+            .data
+            .iter()
+            .for_each(|w| println!("{}\t{}", w.gid, w.name));
+        return;
+    }
+
+    let workspace_gid = matches
+        .value_of(cli::WORKSPACE_GID)
+        .or(positional_workspace_gid)
+        .or(profile.workspace_gid.as_deref());
+    let workspace = match workspace_gid {
+        Some(workspace_gid) => match asana::get_workspace(workspace_gid, pats).await {
+            Ok(workspace) => Some(workspace.data),
+            Err(_) => {
+                eprintln!(
+                    "Error: Failed to access workspace({}), try `asaru doctor`",
+                    workspace_gid
+                );
+                process::exit(1);
+            }
+        },
+        None => None,
     };
 
     let (mut stdout_write, mut file_write);
@@ -31,7 +110,8 @@ async fn main() {
             file_write = OpenOptions::new()
                 .create(true)
                 .write(true)
-                .open(&name)
+                .truncate(true)
+                .open(name)
                 .unwrap_or_else(|_| {
                     eprintln!("Error: Failed to open \"{}\"", name);
                     process::exit(1);
@@ -43,16 +123,43 @@ async fn main() {
             &mut stdout_write
         }
     };
-    terminal::run(workspace_gid, pats)
-        .await
-        .map(|res| {
-            res.iter().for_each(|url| {
-                w.write_all(format!("{}\n", url).as_bytes())
-                    .expect("Error: Failed to print");
-            })
+    let keymap = keymap::Keymap::new(&config.keys).unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    });
+    let theme = theme::Theme::new(config.theme.as_deref(), &config.themes).unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    });
+    let columns = if config.columns.is_empty() {
+        columns::DEFAULT_COLUMNS.to_vec()
+    } else {
+        config.columns.clone()
+    };
+    let height = matches.value_of(cli::HEIGHT).map(|s| {
+        s.parse::<render::Height>()
+            .expect("Error: Failed to parse height")
+    });
+    terminal::run(terminal::Options {
+        workspace: workspace.as_ref(),
+        pats,
+        profile: profile_name,
+        keymap: &keymap,
+        theme: &theme,
+        columns: &columns,
+        order: config.output_order,
+        height,
+        frecency,
+    })
+    .await
+    .map(|res| {
+        res.iter().for_each(|url| {
+            w.write_all(format!("{}\n", url).as_bytes())
+                .expect("Error: Failed to print");
         })
-        .unwrap_or_else(|err| {
-            eprintln!("Error: {}", err);
-            process::exit(1);
-        });
+    })
+    .unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    });
 }
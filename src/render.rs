@@ -0,0 +1,421 @@
+use std::cmp::{max, min};
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use termion::cursor::{self, DetectCursorPos};
+use termion::{clear, style, terminal_size};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const BLANK: &str = " ";
+const ELLIPSIS: &str = "…";
+const MIN_HEIGHT: u16 = 5;
+
+/// Height of the inline mode written as lines (`20`) or a percentage of the terminal (`40%`).
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Height {
+    Lines(u16),
+    Percent(u16),
+}
+
+impl FromStr for Height {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let height = match s.strip_suffix('%') {
+            Some(p) => p
+                .parse::<u16>()
+                .ok()
+                .filter(|&p| p <= 100)
+                .map(Height::Percent),
+            None => s.parse::<u16>().ok().map(Height::Lines),
+        };
+
+        height.ok_or(anyhow!("Failed to parse height \"{}\"", s))
+    }
+}
+
+#[derive(Clone, PartialEq)]
+struct Cell {
+    // NOTE: the right half of a wide character is an empty symbol
+    symbol: String,
+    style: String,
+}
+
+/// Back buffer of the terminal; the coordinates are 1-based like `cursor::Goto`.
+pub(crate) struct Frame {
+    width: u16,
+    height: u16,
+    cells: Vec<Cell>,
+}
+
+impl Frame {
+    pub fn new(width: u16, height: u16, style: &str) -> Self {
+        let cell = Cell {
+            symbol: BLANK.to_string(),
+            style: style.to_string(),
+        };
+        Frame {
+            width,
+            height,
+            cells: vec![cell; width as usize * height as usize],
+        }
+    }
+
+    /// Prints `s` from (x, y) clipping at the right edge, and returns the next column.
+    pub fn print(&mut self, x: u16, y: u16, s: &str, style: &str) -> u16 {
+        if y < 1 || y > self.height {
+            return x;
+        }
+
+        let mut x = x;
+        for g in s.graphemes(true) {
+            let w = grapheme_width(g) as u16;
+            if w == 0 {
+                if let Some(i) = self.index(x - 1, y) {
+                    self.cells[i].symbol.push_str(g);
+                }
+                continue;
+            }
+
+            if x + w - 1 > self.width {
+                break;
+            }
+
+            if let Some(i) = self.index(x, y) {
+                self.cells[i] = Cell {
+                    symbol: g.to_string(),
+                    style: style.to_string(),
+                };
+            }
+            if w == 2 {
+                if let Some(i) = self.index(x + 1, y) {
+                    self.cells[i] = Cell {
+                        symbol: String::new(),
+                        style: style.to_string(),
+                    };
+                }
+            }
+            x += w;
+        }
+
+        x
+    }
+
+    /// Fills the rest of the line from x with blanks.
+    pub fn fill(&mut self, x: u16, y: u16, style: &str) {
+        (x..=self.width).for_each(|x| {
+            self.print(x, y, BLANK, style);
+        });
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    fn index(&self, x: u16, y: u16) -> Option<usize> {
+        if x < 1 || x > self.width || y < 1 || y > self.height {
+            return None;
+        }
+
+        Some((y - 1) as usize * self.width as usize + (x - 1) as usize)
+    }
+}
+
+/// Returns the number of columns to print `s` by `Frame::print`.
+pub(crate) fn width(s: &str) -> usize {
+    s.graphemes(true).map(grapheme_width).sum()
+}
+
+/// Truncates `s` to `width` columns with a trailing ellipsis without splitting grapheme clusters.
+pub(crate) fn truncate(s: &str, width: usize) -> String {
+    if self::width(s) <= width {
+        return s.to_string();
+    }
+
+    let mut col = 0;
+    let mut t = String::new();
+    for g in s.graphemes(true) {
+        let w = grapheme_width(g);
+        if col + w + ELLIPSIS.width() > width {
+            break;
+        }
+        t.push_str(g);
+        col += w;
+    }
+    if width >= ELLIPSIS.width() {
+        t.push_str(ELLIPSIS);
+    }
+
+    t
+}
+
+/// Skips the first `skip` grapheme clusters of `s` with a leading ellipsis, then truncates it.
+pub(crate) fn scroll(s: &str, skip: usize, width: usize) -> String {
+    if skip == 0 {
+        return truncate(s, width);
+    }
+
+    let rest = s.graphemes(true).skip(skip).collect::<String>();
+    format!(
+        "{}{}",
+        ELLIPSIS,
+        truncate(&rest, width.saturating_sub(ELLIPSIS.width()))
+    )
+}
+
+/// Returns whether `s` scrolled by `skip` grapheme clusters still overflows `width` columns.
+pub(crate) fn overflows(s: &str, skip: usize, width: usize) -> bool {
+    let rest = s
+        .graphemes(true)
+        .skip(skip)
+        .map(grapheme_width)
+        .sum::<usize>();
+    let head = if skip > 0 { ELLIPSIS.width() } else { 0 };
+    head + rest > width
+}
+
+// NOTE: a grapheme cluster such as a ZWJ sequence is at most as wide as 2 columns
+fn grapheme_width(g: &str) -> usize {
+    min(g.width(), 2)
+}
+
+/// Writer that emits only the cells changed from the previous frame.
+pub(crate) struct Renderer<W: Write> {
+    out: W,
+    prev: Option<Frame>,
+    // NOTE: the top line and the number of lines in the inline mode
+    inline: Option<(u16, u16)>,
+}
+
+impl<W: Write> Renderer<W> {
+    pub fn new(out: W) -> Self {
+        Renderer {
+            out,
+            prev: None,
+            inline: None,
+        }
+    }
+
+    /// Draws below the current cursor position instead of the whole terminal.
+    pub fn inline(mut out: W, height: Height) -> Result<Self> {
+        let (_, h) = terminal_size()?;
+        let lines = match height {
+            Height::Lines(n) => n,
+            Height::Percent(p) => (h as u32 * p as u32 / 100) as u16,
+        };
+        let lines = min(max(lines, MIN_HEIGHT), h);
+
+        // NOTE: scroll up the terminal to make room for the lines
+        write!(out, "\r{}", "\n".repeat(lines as usize - 1))?;
+        write!(out, "{}", cursor::Up(lines - 1))?;
+        out.flush()?;
+        let (_, top) = out.cursor_pos()?;
+
+        Ok(Renderer {
+            out,
+            prev: None,
+            inline: Some((top, lines)),
+        })
+    }
+
+    /// Returns the size of the area to draw.
+    pub fn size(&self) -> io::Result<(u16, u16)> {
+        let (w, h) = terminal_size()?;
+        match self.inline {
+            Some((top, lines)) => Ok((w, min(lines, (h + 1).saturating_sub(top)))),
+            None => Ok((w, h)),
+        }
+    }
+
+    /// Moves the cursor to (x, y) in the area to draw.
+    pub fn goto(&self, x: u16, y: u16) -> cursor::Goto {
+        match self.inline {
+            Some((top, _)) => cursor::Goto(x, y + top - 1),
+            None => cursor::Goto(x, y),
+        }
+    }
+
+    /// Converts the line of the terminal into the line in the area to draw.
+    pub fn local_y(&self, y: u16) -> Option<u16> {
+        match self.inline {
+            Some((top, lines)) if y >= top && y < top + lines => Some(y - top + 1),
+            Some(_) => None,
+            None => Some(y),
+        }
+    }
+
+    /// Forces the next frame to be drawn entirely, e.g. after something else wrote the terminal.
+    pub fn invalidate(&mut self) {
+        self.prev = None;
+    }
+
+    pub fn draw(&mut self, frame: Frame) -> io::Result<()> {
+        let prev = self
+            .prev
+            .take()
+            .filter(|p| p.width == frame.width && p.height == frame.height);
+        let mut buf = String::new();
+        let mut pos = None;
+        let mut current_style = None;
+        for y in 1..=frame.height {
+            let mut x = 1;
+            while x <= frame.width {
+                let i = frame.index(x, y).expect("out of frame");
+                let cell = &frame.cells[i];
+                let w = match frame.index(x + 1, y) {
+                    Some(j) if frame.cells[j].symbol.is_empty() => 2,
+                    _ => 1,
+                };
+                let changed = match &prev {
+                    Some(p) => (i..i + w as usize).any(|j| p.cells[j] != frame.cells[j]),
+                    None => true,
+                };
+                if changed && !cell.symbol.is_empty() {
+                    if pos != Some((x, y)) {
+                        buf.push_str(&self.goto(x, y).to_string());
+                    }
+                    if current_style != Some(&cell.style) {
+                        buf.push_str(style::Reset.as_ref());
+                        buf.push_str(&cell.style);
+                        current_style = Some(&cell.style);
+                    }
+                    buf.push_str(&cell.symbol);
+                    pos = Some((x + w, y));
+                }
+                x += w;
+            }
+        }
+        if current_style.is_some() {
+            buf.push_str(style::Reset.as_ref());
+        }
+
+        self.out.write_all(buf.as_bytes())?;
+        self.out.flush()?;
+        self.prev = Some(frame);
+
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for Renderer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.out.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl<W: Write> Drop for Renderer<W> {
+    fn drop(&mut self) {
+        // NOTE: clean up the lines in the inline mode so that the shell continues from there
+        if let Some((top, _)) = self.inline {
+            let (_, h) = self.size().unwrap_or((0, 0));
+            let _ = (1..=h)
+                .try_for_each(|y| write!(self.out, "{}{}", self.goto(1, y), clear::CurrentLine));
+            let _ = write!(self.out, "{}{}", cursor::Goto(1, top), cursor::Show);
+            let _ = self.out.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_clips_wide_characters_at_the_right_edge() {
+        let mut frame = Frame::new(3, 1, "");
+        assert_eq!(frame.print(1, 1, "a日本", ""), 4);
+        let mut frame = Frame::new(4, 1, "");
+        assert_eq!(frame.print(2, 1, "日本", ""), 4);
+        assert_eq!(frame.cells[3].symbol, BLANK);
+    }
+
+    #[test]
+    fn draw_only_the_changed_cells() {
+        let reset: &str = style::Reset.as_ref();
+        let mut renderer = Renderer::new(Vec::new());
+        let mut frame = Frame::new(3, 2, "");
+        frame.print(1, 1, "ab", "");
+        renderer.draw(frame).unwrap();
+        assert_eq!(
+            String::from_utf8(renderer.out.clone()).unwrap(),
+            format!(
+                "{}{}ab {}   {}",
+                cursor::Goto(1, 1),
+                reset,
+                cursor::Goto(1, 2),
+                reset
+            )
+        );
+
+        renderer.out.clear();
+        let mut frame = Frame::new(3, 2, "");
+        frame.print(1, 1, "a", "");
+        frame.print(2, 2, "日", "S");
+        renderer.draw(frame).unwrap();
+        assert_eq!(
+            String::from_utf8(renderer.out.clone()).unwrap(),
+            format!(
+                "{}{} {}{}S日{}",
+                cursor::Goto(2, 1),
+                reset,
+                cursor::Goto(2, 2),
+                reset,
+                reset
+            )
+        );
+    }
+
+    #[test]
+    fn draw_the_whole_frame_after_invalidate() {
+        let mut renderer = Renderer::new(Vec::new());
+        renderer.draw(Frame::new(2, 1, "")).unwrap();
+        renderer.out.clear();
+        renderer.draw(Frame::new(2, 1, "")).unwrap();
+        assert!(renderer.out.is_empty());
+        renderer.invalidate();
+        renderer.draw(Frame::new(2, 1, "")).unwrap();
+        assert!(!renderer.out.is_empty());
+    }
+
+    #[test]
+    fn height_from_str() {
+        assert!(matches!("20".parse::<Height>(), Ok(Height::Lines(20))));
+        assert!(matches!("40%".parse::<Height>(), Ok(Height::Percent(40))));
+        assert!("101%".parse::<Height>().is_err());
+        assert!("x".parse::<Height>().is_err());
+    }
+
+    #[test]
+    fn truncate_with_an_ellipsis() {
+        assert_eq!(truncate("abc", 3), "abc");
+        assert_eq!(truncate("abcd", 3), "ab…");
+        assert_eq!(truncate("abcd", 1), "…");
+        assert_eq!(truncate("abcd", 0), "");
+    }
+
+    #[test]
+    fn truncate_does_not_split_wide_characters_or_clusters() {
+        assert_eq!(truncate("日本語", 4), "日…");
+        assert_eq!(truncate("日本語", 5), "日本…");
+        assert_eq!(truncate("e\u{301}e\u{301}e\u{301}", 2), "e\u{301}…");
+        assert_eq!(
+            truncate("👨\u{200d}👩\u{200d}👧ab", 3),
+            "👨\u{200d}👩\u{200d}👧…"
+        );
+    }
+
+    #[test]
+    fn scroll_with_a_leading_ellipsis() {
+        assert_eq!(scroll("abcdef", 0, 4), "abc…");
+        assert_eq!(scroll("abcdef", 2, 4), "…cd…");
+        assert_eq!(scroll("abcdef", 3, 4), "…def");
+        assert!(overflows("abcdef", 2, 4));
+        assert!(!overflows("abcdef", 3, 4));
+    }
+}
@@ -1,267 +1,656 @@
 use std::cmp::min;
-use std::io::{stdin, stdout, Write};
+use std::io::{self, stdin, stdout, Write};
+use std::mem;
 use std::sync::mpsc;
 use std::thread;
 use std::time;
 
 use anyhow::{anyhow, Result};
+use signal_hook::consts::SIGWINCH;
+use signal_hook::iterator::Signals;
 use spinners::{Spinner, Spinners};
-use termion::cursor::{self, DetectCursorPos};
-use termion::event::Key;
-use termion::input::TermRead;
+use termion::cursor;
+use termion::event::{Event, Key, MouseButton, MouseEvent};
+use termion::input::{MouseTerminal, TermRead};
 use termion::raw::IntoRawMode;
-use termion::{clear, color, screen, terminal_size};
-use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+use termion::screen;
 
+use crate::asana;
+use crate::columns;
 use crate::controller;
+use crate::frecency;
+use crate::keymap::{self, Action};
+use crate::render;
+use crate::theme;
 
 const BOL: u16 = 1;
 const BOP: u16 = 3;
 const FIRST_LINE: u16 = 1;
 const PROMPT_LINE: u16 = 3;
+const ERROR_LINE: u16 = 4;
 const RESULTS_LINE: u16 = 5;
-const MENU_BAR: &str = "Asaru | Ctrl-c: Exit | Ctrl-s: Search | TAB: Select | Enter: Execute";
-const CRLF: &str = "\r\n";
+const APP_NAME: &str = "Asaru";
+const HELP_HEADER: &str = "Key bindings";
+const CLOSE_HINT: &str = "any other key: Close";
+const MY_TASKS_MESSAGE: &str = "Loading my tasks";
+const BROWSE_MESSAGE: &str = "Loading";
+const BROWSE_HEADER: &str = "Teams";
+const PATH_SEPARATOR: &str = " › ";
+const WORKSPACES_HEADER: &str = "Select a workspace:";
+const BASKET_HEADER: &str = "Selected tasks";
 const POINT_CURSOR: &str = ">";
+const RANGE_MARK: &str = "*";
 const OPTICAL_RESOLUTIO: u64 = 20;
+const CHECKBOX_WIDTH: u16 = 2;
+const WHEEL_LINES: usize = 3;
+const HSCROLL_STEP: usize = 8;
+const DOUBLE_CLICK: time::Duration = time::Duration::from_millis(400);
 
+enum Input {
+    Event(Event),
+    Resize,
+}
+
+type Inputs = mpsc::Receiver<Option<io::Result<Input>>>;
+
+#[derive(Clone, Copy)]
 enum Mode {
+    Workspaces,
     Prompt,
+    Normal,
     Results,
+    Basket,
+    Failures,
+    Browse,
+}
+
+/// The hints of the keys in the menu bar and the headers, following the key bindings.
+struct Hints {
+    help: String,
+    help_screen: String,
+    browse: String,
+    basket: String,
+    failures: String,
 }
 
-pub(crate) async fn run(workspace_gid: &str, pats: &str) -> Result<Vec<String>> {
-    let mut stdin = stdin().keys();
-    let mut screen = screen::AlternateScreen::from(stdout().into_raw_mode()?);
-    write!(screen, "{}{}", clear::All, color::Fg(color::LightWhite))?;
+impl Hints {
+    fn new(keymap: &keymap::Keymap) -> Self {
+        let actions = keymap.describe(keymap::Context::Results);
+        let key = |action: Action| {
+            actions
+                .iter()
+                .find(|(_, a)| *a == action)
+                .and_then(|(keys, _)| keys.first())
+        };
+        let hint = |labels: &[(Action, &str)]| {
+            labels
+                .iter()
+                .filter_map(|&(action, label)| key(action).map(|k| format!("{}: {}", k, label)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let scroll = [Action::MoveUp, Action::MoveDown]
+            .into_iter()
+            .filter_map(key)
+            .map(|k| k.as_str())
+            .collect::<Vec<_>>();
+        let help_screen = if scroll.is_empty() {
+            CLOSE_HINT.to_string()
+        } else {
+            format!("{}: Scroll, {}", scroll.join("/"), CLOSE_HINT)
+        };
+
+        Hints {
+            help: hint(&[(Action::Help, "Help")]),
+            help_screen,
+            browse: hint(&[
+                (Action::MoveLeft, "Back"),
+                (Action::MoveRight, "Open"),
+                (Action::Select, "Select"),
+                (Action::Execute, "Execute"),
+            ]),
+            basket: hint(&[(Action::Select, "Remove"), (Action::Execute, "Execute")]),
+            failures: hint(&[
+                (Action::Retry, "Retry"),
+                (Action::Execute, "Execute without them"),
+            ]),
+        }
+    }
+}
+
+pub(crate) struct Options<'a> {
+    pub workspace: Option<&'a asana::WorkspacesData>,
+    pub pats: &'a str,
+    pub profile: &'a str,
+    pub keymap: &'a keymap::Keymap,
+    pub theme: &'a theme::Theme,
+    pub columns: &'a [columns::Column],
+    pub order: controller::OutputOrder,
+    pub height: Option<render::Height>,
+    pub frecency: frecency::Frecency,
+}
+
+pub(crate) async fn run(options: Options<'_>) -> Result<Vec<String>> {
+    let Options {
+        workspace,
+        pats,
+        profile,
+        keymap,
+        theme,
+        columns,
+        order,
+        height,
+        frecency,
+    } = options;
+    let mut state = controller::State::new(workspace, profile, pats, columns, frecency);
+    let hints = &Hints::new(keymap);
+    if workspace.is_none() {
+        state = state.list_workspaces().await?;
+        if state.workspaces().is_empty() {
+            return Err(anyhow!("Failed to find accessible workspaces"));
+        }
+    }
+
+    let mut stdin = stdin().events();
+    let mut screen = match height {
+        Some(height) => {
+            let out: Box<dyn Write> = Box::new(MouseTerminal::from(stdout().into_raw_mode()?));
+            render::Renderer::inline(out, height)?
+        }
+        None => {
+            let out: Box<dyn Write> = Box::new(screen::AlternateScreen::from(MouseTerminal::from(
+                stdout().into_raw_mode()?,
+            )));
+            render::Renderer::new(out)
+        }
+    };
 
-    let mut state = controller::State::new(workspace_gid, pats);
-    show_state(&mut screen, &state, None)?;
-    show_cursor(&mut screen, BOP, PROMPT_LINE)?;
-    let mut mode = Mode::Prompt;
+    let mut mode = if workspace.is_some() {
+        show_state(&mut screen, theme, hints, &state, None)?;
+        state = list_my_tasks(&mut screen, state).await?;
+        show_state(&mut screen, theme, hints, &state, None)?;
+        show_prompt_cursor(&mut screen, &state)?;
+        Mode::Prompt
+    } else {
+        show_workspaces(&mut screen, theme, hints, &state)?;
+        hide_cursor(&mut screen)?;
+        Mode::Workspaces
+    };
 
     let (tx, rx) = mpsc::channel();
+    let mut signals = Signals::new([SIGWINCH])?;
+    let sig_tx = tx.clone();
+    thread::spawn(move || -> Result<()> {
+        for _ in signals.forever() {
+            sig_tx.send(Some(Ok(Input::Resize)))?;
+        }
+
+        Ok(())
+    });
     thread::spawn(move || -> Result<()> {
         loop {
-            tx.send(stdin.next())?;
+            tx.send(stdin.next().map(|e| e.map(Input::Event)))?;
             thread::sleep(time::Duration::from_millis(OPTICAL_RESOLUTIO));
         }
     });
 
+    let mut pending = Vec::new();
+    let mut last_click: Option<(time::Instant, usize)> = None;
     let result = loop {
-        if let Some(c) = rx.recv()? {
-            match mode {
-                Mode::Prompt => match c? {
-                    Key::Ctrl('c') => break Ok(Vec::new()),
-                    Key::Char('\n') => {
-                        let sp = wait_state(&mut screen, &state)?;
-                        state = state.search().await?;
-                        // clear keys that are buffering by Receiver during the search
-                        while rx.try_recv().is_ok() {}
-                        sp.stop();
-                        if !state.tasks().is_empty() {
-                            state = state.clear_checked().clear_index();
-                            show_state(&mut screen, &state, Some(state.index()))?;
-                            hide_cursor(&mut screen)?;
-                            mode = Mode::Results;
-                        } else {
-                            state = state.clear_checked();
-                            show_state(&mut screen, &state, None)?;
-                            show_cursor(
-                                &mut screen,
-                                state.text().width() as u16 + BOP,
-                                PROMPT_LINE,
-                            )?;
-                        }
-                    }
-                    Key::Left | Key::Ctrl('b') => {
-                        let (x, _) = screen.cursor_pos()?;
-                        if x > BOP {
-                            let v = state.text().chars().collect::<Vec<char>>();
-                            let w = v
-                                .iter()
-                                .enumerate()
-                                .map(|(i, _)| {
-                                    v[..i + 1]
-                                        .iter()
-                                        .map(|c| c.width().unwrap_or(1))
-                                        .sum::<usize>()
-                                })
-                                .position(|width| width as u16 == x - BOP)
-                                .map(|i| v[i].width().unwrap_or(1))
-                                .unwrap_or(0) as u16;
-
-                            show_cursor(&mut screen, x - w, PROMPT_LINE)?;
-                        }
+        let event = match rx.recv()? {
+            Some(Ok(Input::Event(event))) => event,
+            Some(Ok(Input::Resize)) => {
+                let len = list_len(&state, mode);
+                state = state.fit_viewport(result_rows(&screen)?, len);
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+                continue;
+            }
+            Some(Err(err)) => return Err(err.into()),
+            None => continue,
+        };
+        let action = match event {
+            Event::Key(c) => {
+                let context = match mode {
+                    Mode::Workspaces
+                    | Mode::Results
+                    | Mode::Basket
+                    | Mode::Failures
+                    | Mode::Browse => keymap::Context::Results,
+                    Mode::Prompt => keymap::Context::Prompt,
+                    Mode::Normal => keymap::Context::Normal,
+                };
+                pending.push(c);
+                match keymap.lookup(context, &pending) {
+                    keymap::Lookup::Action(action) => {
+                        pending.clear();
+                        action
                     }
-                    Key::Right | Key::Ctrl('f') => {
-                        let (x, _) = screen.cursor_pos()?;
-                        if x < state.text().width() as u16 + BOP {
-                            let v = state.text().chars().collect::<Vec<char>>();
-                            let w = v
-                                .iter()
-                                .enumerate()
-                                .map(|(i, _)| {
-                                    v[..i].iter().map(|c| c.width().unwrap_or(1)).sum::<usize>()
-                                })
-                                .position(|width| width as u16 == x - BOP)
-                                .map(|i| v[i].width().unwrap_or(1))
-                                .unwrap_or(0) as u16;
-
-                            show_cursor(&mut screen, x + w, PROMPT_LINE)?;
+                    keymap::Lookup::Prefix => continue,
+                    keymap::Lookup::Unbound => {
+                        let mut keys = mem::take(&mut pending);
+                        let mut action = None;
+                        // NOTE: the key that breaks a sequence is looked up again on its own
+                        if let [_, .., last] = keys[..] {
+                            match keymap.lookup(context, &[last]) {
+                                keymap::Lookup::Action(a) => {
+                                    keys.pop();
+                                    action = Some(a);
+                                }
+                                keymap::Lookup::Prefix => {
+                                    keys.pop();
+                                    pending.push(last);
+                                }
+                                keymap::Lookup::Unbound => {}
+                            }
                         }
-                    }
-                    Key::Char(c) => {
-                        let (x, _) = screen.cursor_pos()?;
-                        let mut v = state.text().chars().collect::<Vec<char>>();
-                        let w = v
+                        // NOTE: the printable keys left unbound are typed at the prompt
+                        let typed = keys
                             .iter()
-                            .enumerate()
-                            .map(|(i, _)| {
-                                v[..i + 1]
-                                    .iter()
-                                    .map(|c| c.width().unwrap_or(1))
-                                    .sum::<usize>()
+                            .filter_map(|k| match k {
+                                Key::Char(c) if !c.is_control() => Some(*c),
+                                _ => None,
                             })
-                            .position(|width| width as u16 == x - BOP)
-                            .map(|i| i + 1)
-                            .unwrap_or(0);
-                        v.insert(w, c);
-                        state = state.edit_text(&v.iter().collect::<String>());
-                        show_state(&mut screen, &state, None)?;
-                        show_cursor(&mut screen, x + c.width().unwrap_or(1) as u16, PROMPT_LINE)?;
-                    }
-                    Key::Backspace | Key::Ctrl('h') => {
-                        let (x, _) = screen.cursor_pos()?;
-                        if x > BOP && !state.text().is_empty() {
-                            let mut v = state.text().chars().collect::<Vec<char>>();
-                            let b = v
-                                .iter()
-                                .enumerate()
-                                .map(|(i, _)| {
-                                    v[..i + 1]
-                                        .iter()
-                                        .map(|c| c.width().unwrap_or(1))
-                                        .sum::<usize>()
-                                })
-                                .position(|width| width as u16 == x - BOP);
-                            let w = b.map(|i| v.remove(i).width().unwrap_or(1)).unwrap_or(1) as u16;
-                            state = state.edit_text(&v.iter().collect::<String>());
-                            show_state(&mut screen, &state, None)?;
-                            show_cursor(&mut screen, x - w, PROMPT_LINE)?;
+                            .collect::<Vec<_>>();
+                        if matches!(mode, Mode::Prompt) && !typed.is_empty() {
+                            state = state.edit(|e| typed.iter().for_each(|&c| e.insert(c)));
+                            show_state(&mut screen, theme, hints, &state, None)?;
+                            show_prompt_cursor(&mut screen, &state)?;
                         }
-                    }
-                    Key::Ctrl('a') => {
-                        show_cursor(&mut screen, BOP, PROMPT_LINE)?;
-                    }
-                    Key::Ctrl('e') => {
-                        show_cursor(&mut screen, state.text().width() as u16 + BOP, PROMPT_LINE)?;
-                    }
-                    Key::Ctrl('k') => {
-                        let (x, _) = screen.cursor_pos()?;
-                        let mut v = state.text().chars().collect::<Vec<char>>();
-                        let w = v
-                            .iter()
-                            .enumerate()
-                            .map(|(i, _)| {
-                                v[..i + 1]
-                                    .iter()
-                                    .map(|c| c.width().unwrap_or(1))
-                                    .sum::<usize>()
-                            })
-                            .position(|width| width as u16 == x - BOP)
-                            .map(|i| i + 1)
-                            .unwrap_or(0);
-                        v.truncate(w);
-                        state = state.edit_text(&v.iter().collect::<String>());
-                        show_state(&mut screen, &state, None)?;
-                        show_cursor(&mut screen, state.text().width() as u16 + BOP, PROMPT_LINE)?;
-                    }
-                    Key::Down | Key::Ctrl('n') => {
-                        if !state.tasks().is_empty() {
-                            state = state.clear_index();
-                            show_state(&mut screen, &state, Some(state.index()))?;
-                            hide_cursor(&mut screen)?;
-                            mode = Mode::Results;
+                        match action {
+                            Some(action) => action,
+                            None => continue,
                         }
                     }
-                    Key::Ctrl('g') => {
-                        show_state(&mut screen, &state, None)?;
-                        show_cursor(&mut screen, state.text().width() as u16 + BOP, PROMPT_LINE)?;
+                }
+            }
+            Event::Mouse(MouseEvent::Press(button, x, y)) => {
+                let y = match screen.local_y(y) {
+                    Some(y) => y,
+                    None => continue,
+                };
+                let rows = result_rows(&screen)?;
+                let len = list_len(&state, mode);
+                match button {
+                    MouseButton::WheelUp | MouseButton::WheelDown if len > 0 && rows > 0 => {
+                        let offset = if button == MouseButton::WheelUp {
+                            state.offset().saturating_sub(WHEEL_LINES)
+                        } else {
+                            min(state.offset() + WHEEL_LINES, len.saturating_sub(rows))
+                        };
+                        let index = state.index().clamp(offset, offset + rows - 1);
+                        state = state.edit_offset(offset).edit_index(min(index, len - 1));
+                        redisplay(&mut screen, theme, hints, &state, mode)?;
+                        continue;
                     }
-                    _ => continue,
-                },
-                Mode::Results => match c? {
-                    Key::Ctrl('c') => break Ok(Vec::new()),
-                    Key::Ctrl('s') => {
-                        show_state(&mut screen, &state, None)?;
-                        show_cursor(&mut screen, state.text().width() as u16 + BOP, PROMPT_LINE)?;
+                    MouseButton::Left
+                        if y == PROMPT_LINE
+                            && !matches!(mode, Mode::Workspaces | Mode::Basket | Mode::Browse) =>
+                    {
                         mode = Mode::Prompt;
+                        redisplay(&mut screen, theme, hints, &state, mode)?;
+                        continue;
                     }
-                    Key::Up | Key::Ctrl('p') => {
-                        if state.index() > 0 {
-                            state = state.dec_index();
-                            show_state(&mut screen, &state, Some(state.index()))?;
+                    MouseButton::Left if y >= RESULTS_LINE => {
+                        let index = state.offset() + (y - RESULTS_LINE) as usize;
+                        if index >= len || (y - RESULTS_LINE) as usize >= rows {
+                            continue;
+                        }
+
+                        let double = matches!(
+                            last_click,
+                            Some((t, i)) if i == index && t.elapsed() < DOUBLE_CLICK
+                        );
+                        last_click = if double {
+                            None
                         } else {
-                            show_state(&mut screen, &state, None)?;
-                            show_cursor(
-                                &mut screen,
-                                state.text().width() as u16 + BOP,
-                                PROMPT_LINE,
-                            )?;
-                            mode = Mode::Prompt;
+                            Some((time::Instant::now(), index))
+                        };
+                        state = state.edit_index(index);
+                        if matches!(mode, Mode::Prompt | Mode::Normal) {
+                            mode = Mode::Results;
                         }
-                    }
-                    Key::PageUp | Key::Ctrl('v') => {
-                        state = state.clear_index();
-                        show_state(&mut screen, &state, Some(state.index()))?;
-                    }
-                    Key::Down | Key::Ctrl('n') => {
-                        let (_, h) = terminal_size()?;
-                        if state.index() + 1 < state.tasks().len()
-                            && state.index() as u16 + RESULTS_LINE < h
+                        if double {
+                            Action::Execute
+                        } else if x <= CHECKBOX_WIDTH
+                            && matches!(mode, Mode::Results | Mode::Basket | Mode::Browse)
                         {
-                            state = state.inc_index();
-                            show_state(&mut screen, &state, Some(state.index()))?;
-                        }
-                    }
-                    Key::PageDown | Key::Alt('v') => {
-                        let (_, h) = terminal_size()?;
-                        let lh = state.tasks().len() - 1;
-                        let rh = (h - RESULTS_LINE) as usize;
-                        state = state.edit_index(min(lh, rh));
-                        show_state(&mut screen, &state, Some(state.index()))?;
-                    }
-                    Key::Char('\n') => {
-                        if state.checked().is_empty() {
-                            break state
-                                .get_permalink_url()
-                                .await
-                                .map(|s| vec![s])
-                                .ok_or(anyhow!("Failed to extract permalink_url"));
+                            Action::Select
                         } else {
-                            let urls = state.get_checked_permalink_urls().await;
-                            if state.checked().len() == urls.len() {
-                                break Ok(urls);
-                            } else {
-                                break Err(anyhow!("Failed to extract permalink_url"));
-                            }
+                            redisplay(&mut screen, theme, hints, &state, mode)?;
+                            continue;
                         }
                     }
-                    Key::Char('\t') => {
-                        if state.is_checked(&state.index()) {
-                            state = state.uncheck();
-                        } else {
-                            state = state.check();
-                        }
-                        show_state(&mut screen, &state, Some(state.index()))?;
+                    _ => continue,
+                }
+            }
+            _ => continue,
+        };
+
+        match (mode, action) {
+            (_, Action::Exit) => break Ok(Vec::new()),
+            (_, Action::Help) => {
+                if help(&mut screen, &rx, keymap, theme, hints, &state)? {
+                    break Ok(Vec::new());
+                }
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+            }
+            (Mode::Workspaces, Action::MoveUp) => {
+                if state.index() > 0 {
+                    state = state.dec_index().scroll_into_view(result_rows(&screen)?);
+                    show_workspaces(&mut screen, theme, hints, &state)?;
+                }
+            }
+            (Mode::Workspaces, Action::MoveDown) => {
+                if state.index() + 1 < state.workspaces().len() {
+                    state = state.inc_index().scroll_into_view(result_rows(&screen)?);
+                    show_workspaces(&mut screen, theme, hints, &state)?;
+                }
+            }
+            (Mode::Workspaces, Action::Execute) => {
+                state = state.select_workspace().clear_index();
+                show_state(&mut screen, theme, hints, &state, None)?;
+                state = list_my_tasks(&mut screen, state).await?;
+                while rx.try_recv().is_ok() {}
+                show_state(&mut screen, theme, hints, &state, None)?;
+                show_prompt_cursor(&mut screen, &state)?;
+                mode = Mode::Prompt;
+            }
+            (Mode::Workspaces, Action::Redisplay) => {
+                show_workspaces(&mut screen, theme, hints, &state)?;
+            }
+            (Mode::Prompt | Mode::Normal, Action::Search)
+            | (Mode::Results, Action::CycleSort | Action::ReverseSort) => {
+                state = match action {
+                    Action::CycleSort => state.cycle_sort(),
+                    Action::ReverseSort => state.reverse_sort(),
+                    _ => state,
+                };
+                let sp = wait_state(&mut screen, state.text())?;
+                state = match action {
+                    Action::Search => state.search().await,
+                    _ => state.resort().await,
+                };
+                // clear keys that are buffering by Receiver during the search
+                while rx.try_recv().is_ok() {}
+                sp.stop();
+                // the spinner has written the terminal behind the renderer
+                screen.invalidate();
+                if state.error().is_some() {
+                    // NOTE: stay at the prompt with the text to retry
+                    if matches!(mode, Mode::Results) {
+                        mode = Mode::Prompt;
                     }
-                    Key::Ctrl('g') => {
-                        show_state(&mut screen, &state, Some(state.index()))?;
+                    show_state(&mut screen, theme, hints, &state, None)?;
+                    show_prompt_cursor(&mut screen, &state)?;
+                } else if !state.tasks().is_empty() {
+                    state = state.clear_mark().clear_index();
+                    show_state(&mut screen, theme, hints, &state, Some(state.index()))?;
+                    hide_cursor(&mut screen)?;
+                    mode = Mode::Results;
+                } else {
+                    // NOTE: no results to move in, so go back to the prompt
+                    if matches!(mode, Mode::Results) {
+                        mode = Mode::Prompt;
                     }
-                    _ => continue,
-                },
+                    state = state.clear_mark();
+                    show_state(&mut screen, theme, hints, &state, None)?;
+                    show_prompt_cursor(&mut screen, &state)?;
+                }
+            }
+            (Mode::Prompt, Action::NormalMode) => {
+                mode = Mode::Normal;
+            }
+            (Mode::Normal, Action::InsertMode) => {
+                mode = Mode::Prompt;
+            }
+            (Mode::Prompt | Mode::Normal, Action::MoveLeft) => {
+                state = state.edit(|e| e.move_left());
+                show_state(&mut screen, theme, hints, &state, None)?;
+                show_prompt_cursor(&mut screen, &state)?;
+            }
+            (Mode::Prompt | Mode::Normal, Action::MoveRight) => {
+                state = state.edit(|e| e.move_right());
+                show_state(&mut screen, theme, hints, &state, None)?;
+                show_prompt_cursor(&mut screen, &state)?;
+            }
+            (Mode::Prompt | Mode::Normal, Action::MoveHead) => {
+                state = state.edit(|e| e.move_head());
+                show_state(&mut screen, theme, hints, &state, None)?;
+                show_prompt_cursor(&mut screen, &state)?;
+            }
+            (Mode::Prompt | Mode::Normal, Action::MoveTail) => {
+                state = state.edit(|e| e.move_tail());
+                show_state(&mut screen, theme, hints, &state, None)?;
+                show_prompt_cursor(&mut screen, &state)?;
+            }
+            (Mode::Prompt | Mode::Normal, Action::MoveWordLeft) => {
+                state = state.edit(|e| e.move_word_left());
+                show_state(&mut screen, theme, hints, &state, None)?;
+                show_prompt_cursor(&mut screen, &state)?;
+            }
+            (Mode::Prompt | Mode::Normal, Action::MoveWordRight) => {
+                state = state.edit(|e| e.move_word_right());
+                show_state(&mut screen, theme, hints, &state, None)?;
+                show_prompt_cursor(&mut screen, &state)?;
+            }
+            (Mode::Prompt | Mode::Normal, Action::DeleteBackward) => {
+                state = state.edit(|e| e.delete_backward());
+                show_state(&mut screen, theme, hints, &state, None)?;
+                show_prompt_cursor(&mut screen, &state)?;
+            }
+            (Mode::Prompt | Mode::Normal, Action::DeleteForward) => {
+                state = state.edit(|e| e.delete_forward());
+                show_state(&mut screen, theme, hints, &state, None)?;
+                show_prompt_cursor(&mut screen, &state)?;
+            }
+            (Mode::Prompt | Mode::Normal, Action::KillLine) => {
+                state = state.edit(|e| e.kill_line());
+                show_state(&mut screen, theme, hints, &state, None)?;
+                show_prompt_cursor(&mut screen, &state)?;
+            }
+            (Mode::Prompt | Mode::Normal, Action::KillLineBackward) => {
+                state = state.edit(|e| e.kill_line_backward());
+                show_state(&mut screen, theme, hints, &state, None)?;
+                show_prompt_cursor(&mut screen, &state)?;
+            }
+            (Mode::Prompt | Mode::Normal, Action::KillWordBackward) => {
+                state = state.edit(|e| e.kill_word_backward());
+                show_state(&mut screen, theme, hints, &state, None)?;
+                show_prompt_cursor(&mut screen, &state)?;
+            }
+            (Mode::Prompt | Mode::Normal, Action::KillWordForward) => {
+                state = state.edit(|e| e.kill_word_forward());
+                show_state(&mut screen, theme, hints, &state, None)?;
+                show_prompt_cursor(&mut screen, &state)?;
+            }
+            (Mode::Prompt | Mode::Normal, Action::Yank) => {
+                state = state.edit(|e| e.yank());
+                show_state(&mut screen, theme, hints, &state, None)?;
+                show_prompt_cursor(&mut screen, &state)?;
+            }
+            (Mode::Prompt | Mode::Normal, Action::Undo) => {
+                state = state.edit(|e| e.undo());
+                show_state(&mut screen, theme, hints, &state, None)?;
+                show_prompt_cursor(&mut screen, &state)?;
+            }
+            (Mode::Prompt | Mode::Normal, Action::MoveDown) => {
+                if !state.tasks().is_empty() {
+                    state = state.clear_index();
+                    show_state(&mut screen, theme, hints, &state, Some(state.index()))?;
+                    hide_cursor(&mut screen)?;
+                    mode = Mode::Results;
+                }
+            }
+            (Mode::Prompt | Mode::Normal, Action::Redisplay) => {
+                show_state(&mut screen, theme, hints, &state, None)?;
+                show_prompt_cursor(&mut screen, &state)?;
+            }
+            (Mode::Results, Action::FocusPrompt) => {
+                show_state(&mut screen, theme, hints, &state, None)?;
+                show_prompt_cursor(&mut screen, &state)?;
+                mode = Mode::Prompt;
+            }
+            (Mode::Results | Mode::Basket | Mode::Browse, Action::MoveUp) => {
+                if state.index() > 0 {
+                    state = state.dec_index().scroll_into_view(result_rows(&screen)?);
+                    redisplay(&mut screen, theme, hints, &state, mode)?;
+                } else if matches!(mode, Mode::Results) {
+                    show_state(&mut screen, theme, hints, &state, None)?;
+                    show_prompt_cursor(&mut screen, &state)?;
+                    mode = Mode::Prompt;
+                }
+            }
+            (Mode::Results | Mode::Basket | Mode::Browse, Action::MoveTop) => {
+                state = state.clear_index();
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+            }
+            (Mode::Results | Mode::Basket | Mode::Browse, Action::MoveDown) => {
+                if state.index() + 1 < list_len(&state, mode) {
+                    state = state.inc_index().scroll_into_view(result_rows(&screen)?);
+                    redisplay(&mut screen, theme, hints, &state, mode)?;
+                }
+            }
+            (Mode::Results | Mode::Basket | Mode::Browse, Action::MoveBottom) => {
+                let index = list_len(&state, mode).saturating_sub(1);
+                state = state
+                    .edit_index(index)
+                    .scroll_into_view(result_rows(&screen)?);
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+            }
+            (Mode::Prompt | Mode::Normal | Mode::Results, Action::Browse) => {
+                let sp = wait_state(&mut screen, BROWSE_MESSAGE.to_string())?;
+                state = state.browse().await;
+                while rx.try_recv().is_ok() {}
+                sp.stop();
+                screen.invalidate();
+                if state.is_browsing() {
+                    mode = Mode::Browse;
+                }
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+            }
+            (Mode::Browse, Action::FocusPrompt) => {
+                state = state.leave_browse();
+                mode = Mode::Prompt;
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+            }
+            (Mode::Browse, Action::MoveLeft) if state.hscroll() == 0 => {
+                state = state.back().scroll_into_view(result_rows(&screen)?);
+                if !state.is_browsing() {
+                    mode = leave_basket(&state);
+                }
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+            }
+            (Mode::Browse, Action::MoveRight | Action::Execute)
+                if state.can_open(action == Action::Execute) =>
+            {
+                let sp = wait_state(&mut screen, BROWSE_MESSAGE.to_string())?;
+                state = state.open().await;
+                while rx.try_recv().is_ok() {}
+                sp.stop();
+                screen.invalidate();
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+            }
+            (Mode::Results | Mode::Basket | Mode::Browse, Action::MoveLeft) => {
+                if state.hscroll() > 0 {
+                    let hscroll = state.hscroll().saturating_sub(HSCROLL_STEP);
+                    state = state.edit_hscroll(hscroll);
+                    redisplay(&mut screen, theme, hints, &state, mode)?;
+                }
+            }
+            (Mode::Results | Mode::Basket | Mode::Browse, Action::MoveRight) => {
+                let (w, _) = screen.size()?;
+                let tasks = list(&state, mode);
+                let layout = state.layout(tasks, text_width(w));
+                let overflows = tasks
+                    .get(state.index())
+                    .map(|t| layout.name_overflows(t, state.hscroll()))
+                    .unwrap_or(false);
+                if overflows {
+                    let hscroll = state.hscroll() + HSCROLL_STEP;
+                    state = state.edit_hscroll(hscroll);
+                    redisplay(&mut screen, theme, hints, &state, mode)?;
+                }
+            }
+            (Mode::Prompt | Mode::Normal | Mode::Results | Mode::Browse, Action::ShowBasket) => {
+                if !state.basket().is_empty() {
+                    state = state.clear_mark().clear_index();
+                    mode = Mode::Basket;
+                    redisplay(&mut screen, theme, hints, &state, mode)?;
+                }
+            }
+            (Mode::Basket, Action::ShowBasket | Action::FocusPrompt) => {
+                state = state.clear_index();
+                mode = leave_basket(&state);
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+            }
+            (Mode::Basket, Action::Select) => {
+                state = state.remove_from_basket();
+                if state.basket().is_empty() {
+                    state = state.clear_index();
+                    mode = leave_basket(&state);
+                } else {
+                    state = state.scroll_into_view(result_rows(&screen)?);
+                }
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+            }
+            (Mode::Basket, Action::DeselectAll) => {
+                state = state.clear_basket().clear_index();
+                mode = leave_basket(&state);
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+            }
+            (Mode::Results | Mode::Basket | Mode::Browse, Action::Execute)
+            | (Mode::Failures, Action::Retry) => {
+                // NOTE: an empty listing such as a section without tasks has nothing to output
+                if state.targets(order).is_empty() {
+                    continue;
+                }
+
+                state = state.resolve_permalink_urls(order).await;
+                if state.failures().is_empty() {
+                    break Ok(output(state, order));
+                }
+
+                mode = Mode::Failures;
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+            }
+            (Mode::Failures, Action::Execute) => {
+                break Ok(output(state, order));
+            }
+            (Mode::Failures, Action::FocusPrompt) => {
+                mode = leave_basket(&state);
+                redisplay(&mut screen, theme, hints, &state, mode)?;
             }
+            (Mode::Failures, Action::Redisplay) => {
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+            }
+            (Mode::Results | Mode::Browse, Action::Select) => {
+                if state.is_checked(&state.index()) {
+                    state = state.uncheck();
+                } else {
+                    state = state.check();
+                }
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+            }
+            (Mode::Results | Mode::Browse, Action::SelectAll) => {
+                state = state.check_all();
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+            }
+            (Mode::Results | Mode::Browse, Action::DeselectAll) => {
+                state = state.uncheck_all();
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+            }
+            (Mode::Results | Mode::Browse, Action::InvertSelection) => {
+                state = state.invert_checked();
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+            }
+            (Mode::Results | Mode::Browse, Action::MarkRange) => {
+                state = state.mark_range();
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+            }
+            (Mode::Results | Mode::Basket | Mode::Browse, Action::Redisplay) => {
+                redisplay(&mut screen, theme, hints, &state, mode)?;
+            }
+            _ => continue,
         }
     };
     write!(screen, "{}", cursor::Show)?;
@@ -271,118 +660,499 @@ pub(crate) async fn run(workspace_gid: &str, pats: &str) -> Result<Vec<String>>
 }
 
 fn show_state<W: Write>(
-    screen: &mut W,
+    screen: &mut render::Renderer<W>,
+    theme: &theme::Theme,
+    hints: &Hints,
     state: &controller::State,
     opt: Option<usize>,
 ) -> Result<()> {
-    let (w, _) = terminal_size()?;
-    write!(screen, "{}{}", clear::All, cursor::Goto(BOL, FIRST_LINE))?;
+    let mut frame = new_frame(screen, theme)?;
+    draw_menu_bar(&mut frame, theme, hints, state)?;
+    frame.print(BOL, PROMPT_LINE, "$ ", &theme.reset());
+    let (text, _) = state.editor().view(text_width(frame.width()));
+    frame.print(BOP, PROMPT_LINE, &text, &theme.reset());
+    draw_error(&mut frame, theme, state);
+    let rows = result_rows(screen)?;
+    draw_titles(&mut frame, theme, state, state.tasks(), opt, rows);
+    screen.draw(frame)?;
+
+    Ok(())
+}
+
+fn show_failures<W: Write>(
+    screen: &mut render::Renderer<W>,
+    theme: &theme::Theme,
+    hints: &Hints,
+    state: &controller::State,
+) -> Result<()> {
+    let mut frame = new_frame(screen, theme)?;
+    let w = frame.width();
+    draw_menu_bar(&mut frame, theme, hints, state)?;
+    let header = format!("Failed to get {} URL(s)", state.failures().len());
+    let header = format!("{}:", with_hint(&header, &hints.failures));
+    frame.print(
+        BOL,
+        PROMPT_LINE,
+        &render::truncate(&header, w as usize),
+        &theme.reset(),
+    );
+
+    state
+        .failures()
+        .iter()
+        .take(result_rows(screen)?)
+        .zip(RESULTS_LINE..)
+        .for_each(|((t, reason), y)| {
+            let s = render::truncate(&format!("{}: {}", t.name, reason), text_width(w));
+            frame.print(BOP, y, &s, &format!("{}{}", theme.base, theme.overdue));
+        });
+    screen.draw(frame)?;
+
+    Ok(())
+}
+
+/// Shows the key bindings until a key other than the scrolling ones is pressed, and returns
+/// whether the key was bound to exit.
+fn help<W: Write>(
+    screen: &mut render::Renderer<W>,
+    rx: &Inputs,
+    keymap: &keymap::Keymap,
+    theme: &theme::Theme,
+    hints: &Hints,
+    state: &controller::State,
+) -> Result<bool> {
+    let lines = help_lines(keymap);
+    let mut offset = 0;
+    let mut pending = Vec::new();
+    hide_cursor(screen)?;
+    loop {
+        let bottom = lines.len().saturating_sub(result_rows(screen)?);
+        offset = min(offset, bottom);
+        show_help(screen, theme, hints, state, &lines, offset)?;
+        let event = match rx.recv()? {
+            Some(Ok(Input::Event(event))) => event,
+            Some(Ok(Input::Resize)) | None => continue,
+            Some(Err(err)) => return Err(err.into()),
+        };
+        match event {
+            Event::Key(c) => {
+                pending.push(c);
+                let action = match keymap.lookup(keymap::Context::Results, &pending) {
+                    keymap::Lookup::Action(action) => action,
+                    keymap::Lookup::Prefix => continue,
+                    keymap::Lookup::Unbound => return Ok(false),
+                };
+                pending.clear();
+                match action {
+                    Action::MoveUp => offset = offset.saturating_sub(1),
+                    Action::MoveDown => offset += 1,
+                    Action::MoveTop => offset = 0,
+                    Action::MoveBottom => offset = bottom,
+                    Action::Exit => return Ok(true),
+                    _ => return Ok(false),
+                }
+            }
+            Event::Mouse(MouseEvent::Press(MouseButton::WheelUp, _, _)) => {
+                offset = offset.saturating_sub(WHEEL_LINES);
+            }
+            Event::Mouse(MouseEvent::Press(MouseButton::WheelDown, _, _)) => {
+                offset += WHEEL_LINES;
+            }
+            _ => continue,
+        }
+    }
+}
+
+/// Returns the lines of the key bindings of each context with the descriptions of the actions.
+fn help_lines(keymap: &keymap::Keymap) -> Vec<String> {
+    let contexts = [
+        ("Prompt", keymap.describe(keymap::Context::Prompt)),
+        ("Normal mode", keymap.describe(keymap::Context::Normal)),
+        ("Results", keymap.describe(keymap::Context::Results)),
+    ];
+    let keys_width = contexts
+        .iter()
+        .flat_map(|(_, actions)| actions.iter())
+        .map(|(keys, _)| render::width(&keys.join(" / ")))
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = Vec::new();
+    for (title, actions) in contexts.iter().filter(|(_, a)| !a.is_empty()) {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push(format!("{}:", title));
+        for (keys, action) in actions {
+            let keys = keys.join(" / ");
+            let padding = " ".repeat(keys_width - render::width(&keys));
+            lines.push(format!("  {}{}  {}", keys, padding, action.description()));
+        }
+    }
+
+    lines
+}
+
+fn show_help<W: Write>(
+    screen: &mut render::Renderer<W>,
+    theme: &theme::Theme,
+    hints: &Hints,
+    state: &controller::State,
+    lines: &[String],
+    offset: usize,
+) -> Result<()> {
+    let mut frame = new_frame(screen, theme)?;
+    let w = frame.width();
+    draw_menu_bar(&mut frame, theme, hints, state)?;
+    let header = format!("{}:", with_hint(HELP_HEADER, &hints.help_screen));
+    let header = render::truncate(&header, w as usize);
+    frame.print(BOL, PROMPT_LINE, &header, &theme.reset());
+
+    lines
+        .iter()
+        .skip(offset)
+        .take(result_rows(screen)?)
+        .zip(RESULTS_LINE..)
+        .for_each(|(s, y)| {
+            frame.print(BOP, y, &render::truncate(s, text_width(w)), &theme.reset());
+        });
+    screen.draw(frame)?;
+
+    Ok(())
+}
+
+fn show_browse<W: Write>(
+    screen: &mut render::Renderer<W>,
+    theme: &theme::Theme,
+    hints: &Hints,
+    state: &controller::State,
+) -> Result<()> {
+    let mut frame = new_frame(screen, theme)?;
+    draw_menu_bar(&mut frame, theme, hints, state)?;
+    let path = state
+        .path()
+        .iter()
+        .map(|t| t.name.as_str())
+        .collect::<Vec<_>>();
+    let header = format!(
+        "{}{}{}",
+        BROWSE_HEADER,
+        if path.is_empty() { "" } else { PATH_SEPARATOR },
+        path.join(PATH_SEPARATOR),
+    );
+    let header = with_hint(&header, &hints.browse);
+    let header = render::truncate(&header, frame.width() as usize);
+    frame.print(BOL, PROMPT_LINE, &header, &theme.reset());
+    draw_error(&mut frame, theme, state);
+    let rows = result_rows(screen)?;
+    draw_titles(
+        &mut frame,
+        theme,
+        state,
+        state.tasks(),
+        Some(state.index()),
+        rows,
+    );
+    screen.draw(frame)?;
+
+    Ok(())
+}
+
+fn show_basket<W: Write>(
+    screen: &mut render::Renderer<W>,
+    theme: &theme::Theme,
+    hints: &Hints,
+    state: &controller::State,
+) -> Result<()> {
+    let mut frame = new_frame(screen, theme)?;
+    draw_menu_bar(&mut frame, theme, hints, state)?;
+    let header = format!("{}:", with_hint(BASKET_HEADER, &hints.basket));
+    let header = render::truncate(&header, frame.width() as usize);
+    frame.print(BOL, PROMPT_LINE, &header, &theme.reset());
+    draw_error(&mut frame, theme, state);
+    let rows = result_rows(screen)?;
+    draw_titles(
+        &mut frame,
+        theme,
+        state,
+        state.basket(),
+        Some(state.index()),
+        rows,
+    );
+    screen.draw(frame)?;
+
+    Ok(())
+}
+
+fn show_workspaces<W: Write>(
+    screen: &mut render::Renderer<W>,
+    theme: &theme::Theme,
+    hints: &Hints,
+    state: &controller::State,
+) -> Result<()> {
+    let mut frame = new_frame(screen, theme)?;
+    let w = frame.width();
+    draw_menu_bar(&mut frame, theme, hints, state)?;
+    frame.print(BOL, PROMPT_LINE, WORKSPACES_HEADER, &theme.reset());
+
+    state
+        .get_workspace_names()
+        .iter()
+        .enumerate()
+        .skip(state.offset())
+        .take(result_rows(screen)?)
+        .zip(RESULTS_LINE..)
+        .for_each(|((i, s), y)| {
+            let s = &render::truncate(s, text_width(w));
+            if i == state.index() {
+                frame.print(BOL, y, POINT_CURSOR, &format!("{}", theme.pointer));
+                frame.print(BOP, y, s, &format!("{}{}", theme.base, theme.current));
+            } else {
+                frame.print(BOP, y, s, &theme.reset());
+            }
+        });
+    screen.draw(frame)?;
+
+    Ok(())
+}
+
+fn new_frame<W: Write>(
+    screen: &render::Renderer<W>,
+    theme: &theme::Theme,
+) -> Result<render::Frame> {
+    let (w, h) = screen.size()?;
+    Ok(render::Frame::new(w, h, &theme.reset()))
+}
 
-    let menu_bar = if MENU_BAR.len() > w as usize {
-        &MENU_BAR[..w as usize]
+fn draw_menu_bar(
+    frame: &mut render::Frame,
+    theme: &theme::Theme,
+    hints: &Hints,
+    state: &controller::State,
+) -> Result<()> {
+    let style = format!("{}{}", theme.base, theme.menu_bar);
+    let mut segments = vec![APP_NAME.to_string()];
+    if !state.workspace_name().is_empty() {
+        segments.push(state.workspace_name().to_string());
+    }
+    segments.push(format!("profile: {}", state.profile()));
+    // NOTE: the results and the latency are shown after the first search
+    if let Some(latency) = state.latency() {
+        let more = if state.has_more() {
+            " (more available)"
+        } else {
+            ""
+        };
+        let results = match state.tasks().len() {
+            1 => "1 result".to_string(),
+            n => format!("{} results", n),
+        };
+        segments.push(format!("{}{}", results, more));
+        segments.push(format!("{} selected", state.basket().len()));
+        segments.push(format!("Sort: {}", state.sort()));
+        segments.push(format!("{}ms", latency.as_millis()));
     } else {
-        MENU_BAR
-    };
-    write!(
-        screen,
-        "{}{:width$}{}{}{}",
-        color::Bg(color::LightMagenta),
-        menu_bar,
-        color::Bg(color::Reset),
-        CRLF,
-        CRLF,
-        width = w as usize,
-    )?;
-
-    write!(
-        screen,
-        "$ {}{}{}",
-        state.text(),
-        CRLF,
-        get_titles(state, opt)?,
-    )?;
-    screen.flush()?;
+        segments.push(format!("{} selected", state.basket().len()));
+        segments.push(format!("Sort: {}", state.sort()));
+    }
+    if !hints.help.is_empty() {
+        segments.push(hints.help.clone());
+    }
+    let menu_bar = render::truncate(&segments.join(" | "), frame.width() as usize);
+    let x = frame.print(BOL, FIRST_LINE, &menu_bar, &style);
+    frame.fill(x, FIRST_LINE, &style);
 
     Ok(())
 }
 
-fn wait_state<W: Write>(screen: &mut W, state: &controller::State) -> Result<Spinner> {
-    write!(screen, "{}", cursor::Goto(BOL, PROMPT_LINE))?;
-    Ok(Spinner::new(&Spinners::Dots9, state.text().to_string()))
+/// Appends the hint of the keys in parentheses unless no key is bound.
+fn with_hint(s: &str, hint: &str) -> String {
+    if hint.is_empty() {
+        s.to_string()
+    } else {
+        format!("{} ({})", s, hint)
+    }
+}
+
+fn draw_error(frame: &mut render::Frame, theme: &theme::Theme, state: &controller::State) {
+    if let Some(err) = state.error() {
+        let s = render::truncate(&format!("Error: {}", err), frame.width() as usize);
+        frame.print(
+            BOL,
+            ERROR_LINE,
+            &s,
+            &format!("{}{}", theme.base, theme.error),
+        );
+    }
+}
+
+fn redisplay<W: Write>(
+    screen: &mut render::Renderer<W>,
+    theme: &theme::Theme,
+    hints: &Hints,
+    state: &controller::State,
+    mode: Mode,
+) -> Result<()> {
+    match mode {
+        Mode::Workspaces => {
+            show_workspaces(screen, theme, hints, state)?;
+            hide_cursor(screen)
+        }
+        Mode::Prompt | Mode::Normal => {
+            show_state(screen, theme, hints, state, None)?;
+            show_prompt_cursor(screen, state)
+        }
+        Mode::Results => {
+            show_state(screen, theme, hints, state, Some(state.index()))?;
+            hide_cursor(screen)
+        }
+        Mode::Basket => {
+            show_basket(screen, theme, hints, state)?;
+            hide_cursor(screen)
+        }
+        Mode::Failures => {
+            show_failures(screen, theme, hints, state)?;
+            hide_cursor(screen)
+        }
+        Mode::Browse => {
+            show_browse(screen, theme, hints, state)?;
+            hide_cursor(screen)
+        }
+    }
+}
+
+/// Records the targets in the frecency and returns their URLs to output.
+fn output(state: controller::State, order: controller::OutputOrder) -> Vec<String> {
+    let state = state.record_targets(order);
+    // NOTE: failing to save the frecency must not lose the output
+    let _ = state.frecency().save();
+    state.get_permalink_urls(order)
+}
+
+/// Returns the mode to go back to from the basket.
+fn leave_basket(state: &controller::State) -> Mode {
+    if state.is_browsing() {
+        Mode::Browse
+    } else if state.tasks().is_empty() {
+        Mode::Prompt
+    } else {
+        Mode::Results
+    }
+}
+
+fn list_len(state: &controller::State, mode: Mode) -> usize {
+    match mode {
+        Mode::Workspaces => state.workspaces().len(),
+        // NOTE: the failures are not rows to move in or to click
+        Mode::Failures => 0,
+        _ => list(state, mode).len(),
+    }
+}
+
+/// Returns the tasks listed in the mode.
+fn list(state: &controller::State, mode: Mode) -> &[asana::Resource] {
+    match mode {
+        Mode::Basket => state.basket(),
+        _ => state.tasks(),
+    }
+}
+
+/// Returns the number of lines available for the results.
+fn result_rows<W: Write>(screen: &render::Renderer<W>) -> Result<usize> {
+    let (_, h) = screen.size()?;
+    Ok((h + 1).saturating_sub(RESULTS_LINE) as usize)
+}
+
+fn wait_state<W: Write>(screen: &mut render::Renderer<W>, message: String) -> Result<Spinner> {
+    write!(screen, "{}", screen.goto(BOL, PROMPT_LINE))?;
+    Ok(Spinner::new(&Spinners::Dots9, message))
+}
+
+/// Lists my tasks as the start screen with the spinner at the prompt.
+async fn list_my_tasks<W: Write>(
+    screen: &mut render::Renderer<W>,
+    state: controller::State,
+) -> Result<controller::State> {
+    let sp = wait_state(screen, MY_TASKS_MESSAGE.to_string())?;
+    let state = state.list_my_tasks().await;
+    sp.stop();
+    // the spinner has written the terminal behind the renderer
+    screen.invalidate();
+
+    Ok(state)
 }
 
-fn get_titles(state: &controller::State, opt: Option<usize>) -> Result<String> {
-    let (w, h) = terminal_size()?;
-    let mut titles = state.get_titles();
-    titles.truncate((h - PROMPT_LINE - 1) as usize);
-    Ok(titles
-        .iter_mut()
+fn draw_titles(
+    frame: &mut render::Frame,
+    theme: &theme::Theme,
+    state: &controller::State,
+    tasks: &[asana::Resource],
+    opt: Option<usize>,
+    rows: usize,
+) {
+    let layout = state.layout(tasks, text_width(frame.width()));
+    tasks
+        .iter()
         .enumerate()
-        .map(|(i, s)| {
-            unicode_trancate(s, w as usize - 2);
+        .skip(state.offset())
+        .take(rows)
+        .zip(RESULTS_LINE..)
+        .for_each(|((i, t), y)| {
+            let selected = if state.in_basket(t) {
+                theme.selected.to_string()
+            } else {
+                String::new()
+            };
+            let status = if t.completed {
+                theme.completed.to_string()
+            } else if layout.is_overdue(t) {
+                theme.overdue.to_string()
+            } else {
+                String::new()
+            };
             match opt {
-                Some(index) if i == index && state.is_checked(&i) => {
-                    format!(
-                        "{}{}{}{} {}{}{}",
-                        CRLF,
-                        color::Fg(color::Magenta),
-                        POINT_CURSOR,
-                        color::Fg(color::LightWhite),
-                        color::Bg(color::Magenta),
-                        s,
-                        color::Bg(color::Reset),
-                    )
-                }
                 Some(index) if i == index => {
-                    format!(
-                        "{}{}{}{} {}",
-                        CRLF,
-                        color::Fg(color::Magenta),
-                        POINT_CURSOR,
-                        color::Fg(color::LightWhite),
-                        s
-                    )
+                    let s = layout.row(t, state.hscroll());
+                    frame.print(BOL, y, POINT_CURSOR, &format!("{}", theme.pointer));
+                    let style = format!("{}{}{}{}", theme.base, status, theme.current, selected);
+                    frame.print(BOP, y, &s, &style);
                 }
-                Some(_) if state.is_checked(&i) => {
-                    format!(
-                        "{}  {}{}{}",
-                        CRLF,
-                        color::Bg(color::Magenta),
-                        s,
-                        color::Bg(color::Reset),
-                    )
+                _ => {
+                    let s = layout.row(t, 0);
+                    let style = format!("{}{}{}", theme.base, status, selected);
+                    frame.print(BOP, y, &s, &style);
                 }
-                None if state.is_checked(&i) => {
-                    format!(
-                        "{}  {}{}{}",
-                        CRLF,
-                        color::Bg(color::Magenta),
-                        s,
-                        color::Bg(color::Reset),
-                    )
-                }
-                _ => format!("{}  {}", CRLF, s),
             }
-        })
-        .collect())
+            if state.mark() == Some(i) {
+                frame.print(BOL + 1, y, RANGE_MARK, &format!("{}", theme.pointer));
+            }
+        });
 }
 
-fn show_cursor<W: Write>(screen: &mut W, x: u16, y: u16) -> Result<()> {
-    write!(screen, "{}{}", cursor::Goto(x, y), cursor::Show)?;
+fn show_cursor<W: Write>(screen: &mut render::Renderer<W>, x: u16, y: u16) -> Result<()> {
+    write!(screen, "{}{}", screen.goto(x, y), cursor::Show)?;
     screen.flush()?;
 
     Ok(())
 }
 
+fn show_prompt_cursor<W: Write>(
+    screen: &mut render::Renderer<W>,
+    state: &controller::State,
+) -> Result<()> {
+    let (w, _) = screen.size()?;
+    let (_, x) = state.editor().view(text_width(w));
+    show_cursor(screen, x as u16 + BOP, PROMPT_LINE)
+}
+
+/// Returns the number of columns from `BOP` to the right edge for the prompt and the titles.
+fn text_width(width: u16) -> usize {
+    (width + 1).saturating_sub(BOP) as usize
+}
+
 fn hide_cursor<W: Write>(screen: &mut W) -> Result<()> {
     write!(screen, "{}", cursor::Hide)?;
     screen.flush()?;
 
     Ok(())
 }
-
-fn unicode_trancate(s: &mut String, max_size: usize) {
-    while s.width() > max_size {
-        s.pop();
-    }
-}
@@ -0,0 +1,314 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use termion::{color, style};
+
+pub const DARK: &str = "dark";
+pub const LIGHT: &str = "light";
+const NAMED_COLORS: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "light-black",
+    "light-red",
+    "light-green",
+    "light-yellow",
+    "light-blue",
+    "light-magenta",
+    "light-cyan",
+    "light-white",
+];
+
+/// Color written as a name (`magenta`), a 256-color index (`213`) or a truecolor (`#ff00ff`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub(crate) enum Color {
+    Ansi(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl TryFrom<String> for Color {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        if let Some(i) = NAMED_COLORS.iter().position(|&n| n == s) {
+            return Ok(Color::Ansi(i as u8));
+        }
+
+        if let Ok(i) = s.parse::<u8>() {
+            return Ok(Color::Ansi(i));
+        }
+
+        match s.strip_prefix('#') {
+            Some(hex) if hex.len() == 6 => {
+                let v = u32::from_str_radix(hex, 16)
+                    .map_err(|_| anyhow!("Failed to parse color \"{}\"", s))?;
+                Ok(Color::Rgb((v >> 16) as u8, (v >> 8) as u8, v as u8))
+            }
+            _ => Err(anyhow!("Failed to parse color \"{}\"", s)),
+        }
+    }
+}
+
+impl From<Color> for String {
+    fn from(c: Color) -> Self {
+        match c {
+            Color::Ansi(i) if (i as usize) < NAMED_COLORS.len() => NAMED_COLORS[i as usize].into(),
+            Color::Ansi(i) => i.to_string(),
+            Color::Rgb(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Style {
+    fg: Option<Color>,
+    bg: Option<Color>,
+    bold: bool,
+    faint: bool,
+    reverse: bool,
+    underline: bool,
+    crossed_out: bool,
+}
+
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.fg {
+            Some(Color::Ansi(i)) => write!(f, "{}", color::Fg(color::AnsiValue(i)))?,
+            Some(Color::Rgb(r, g, b)) => write!(f, "{}", color::Fg(color::Rgb(r, g, b)))?,
+            None => {}
+        }
+        match self.bg {
+            Some(Color::Ansi(i)) => write!(f, "{}", color::Bg(color::AnsiValue(i)))?,
+            Some(Color::Rgb(r, g, b)) => write!(f, "{}", color::Bg(color::Rgb(r, g, b)))?,
+            None => {}
+        }
+        if self.bold {
+            write!(f, "{}", style::Bold)?;
+        }
+        if self.faint {
+            write!(f, "{}", style::Faint)?;
+        }
+        if self.reverse {
+            write!(f, "{}", style::Invert)?;
+        }
+        if self.underline {
+            write!(f, "{}", style::Underline)?;
+        }
+        if self.crossed_out {
+            write!(f, "{}", style::CrossedOut)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, Clone)]
+pub(crate) struct ThemeConfig {
+    pub base: Option<String>,
+    pub fg: Option<Color>,
+    pub menu_bar_fg: Option<Color>,
+    pub menu_bar_bg: Option<Color>,
+    pub pointer_fg: Option<Color>,
+    pub selected_fg: Option<Color>,
+    pub selected_bg: Option<Color>,
+    pub completed_fg: Option<Color>,
+    pub overdue_fg: Option<Color>,
+    pub error_fg: Option<Color>,
+}
+
+pub(crate) struct Theme {
+    pub base: Style,
+    pub menu_bar: Style,
+    pub pointer: Style,
+    pub current: Style,
+    pub selected: Style,
+    pub completed: Style,
+    pub overdue: Style,
+    pub error: Style,
+}
+
+impl Theme {
+    pub fn new(name: Option<&str>, themes: &BTreeMap<String, ThemeConfig>) -> Result<Self> {
+        // NOTE: https://no-color.org/
+        if env::var("NO_COLOR").map(|v| !v.is_empty()).unwrap_or(false) {
+            return Ok(monochrome());
+        }
+
+        let name = name.unwrap_or(DARK);
+        match name {
+            DARK => return Ok(dark()),
+            LIGHT => return Ok(light()),
+            _ => {}
+        }
+
+        let config = themes
+            .get(name)
+            .ok_or(anyhow!("Failed to find theme \"{}\"", name))?;
+        let mut theme = match config.base.as_deref().unwrap_or(DARK) {
+            DARK => dark(),
+            LIGHT => light(),
+            base => return Err(anyhow!("Failed to find base theme \"{}\"", base)),
+        };
+        theme.base.fg = config.fg.or(theme.base.fg);
+        theme.menu_bar.fg = config.menu_bar_fg.or(theme.menu_bar.fg);
+        theme.menu_bar.bg = config.menu_bar_bg.or(theme.menu_bar.bg);
+        theme.pointer.fg = config.pointer_fg.or(theme.pointer.fg);
+        theme.selected.fg = config.selected_fg.or(theme.selected.fg);
+        theme.selected.bg = config.selected_bg.or(theme.selected.bg);
+        theme.completed.fg = config.completed_fg.or(theme.completed.fg);
+        theme.overdue.fg = config.overdue_fg.or(theme.overdue.fg);
+        theme.error.fg = config.error_fg.or(theme.error.fg);
+
+        Ok(theme)
+    }
+
+    /// Returns the escape sequence to go back to the base style.
+    pub fn reset(&self) -> String {
+        format!("{}{}", style::Reset, self.base)
+    }
+}
+
+fn dark() -> Theme {
+    Theme {
+        base: Style {
+            fg: Some(Color::Ansi(15)),
+            ..Default::default()
+        },
+        menu_bar: Style {
+            bg: Some(Color::Ansi(13)),
+            ..Default::default()
+        },
+        pointer: Style {
+            fg: Some(Color::Ansi(5)),
+            ..Default::default()
+        },
+        current: Style::default(),
+        selected: Style {
+            bg: Some(Color::Ansi(5)),
+            ..Default::default()
+        },
+        completed: Style {
+            fg: Some(Color::Ansi(8)),
+            crossed_out: true,
+            ..Default::default()
+        },
+        overdue: Style {
+            fg: Some(Color::Ansi(9)),
+            ..Default::default()
+        },
+        error: Style {
+            fg: Some(Color::Ansi(9)),
+            ..Default::default()
+        },
+    }
+}
+
+fn light() -> Theme {
+    Theme {
+        base: Style {
+            fg: Some(Color::Ansi(0)),
+            ..Default::default()
+        },
+        menu_bar: Style {
+            fg: Some(Color::Ansi(15)),
+            bg: Some(Color::Ansi(5)),
+            ..Default::default()
+        },
+        pointer: Style {
+            fg: Some(Color::Ansi(5)),
+            ..Default::default()
+        },
+        current: Style::default(),
+        selected: Style {
+            fg: Some(Color::Ansi(0)),
+            bg: Some(Color::Ansi(13)),
+            ..Default::default()
+        },
+        completed: Style {
+            fg: Some(Color::Ansi(8)),
+            crossed_out: true,
+            ..Default::default()
+        },
+        overdue: Style {
+            fg: Some(Color::Ansi(1)),
+            ..Default::default()
+        },
+        error: Style {
+            fg: Some(Color::Ansi(1)),
+            ..Default::default()
+        },
+    }
+}
+
+fn monochrome() -> Theme {
+    Theme {
+        base: Style::default(),
+        menu_bar: Style {
+            reverse: true,
+            ..Default::default()
+        },
+        pointer: Style::default(),
+        current: Style {
+            reverse: true,
+            ..Default::default()
+        },
+        selected: Style {
+            underline: true,
+            ..Default::default()
+        },
+        completed: Style {
+            faint: true,
+            crossed_out: true,
+            ..Default::default()
+        },
+        overdue: Style {
+            bold: true,
+            ..Default::default()
+        },
+        error: Style {
+            bold: true,
+            ..Default::default()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color(s: &str) -> Result<Color> {
+        Color::try_from(s.to_string())
+    }
+
+    #[test]
+    fn color_from_a_name_an_index_or_a_truecolor() {
+        assert_eq!(color("black").unwrap(), Color::Ansi(0));
+        assert_eq!(color("light-white").unwrap(), Color::Ansi(15));
+        assert_eq!(color("213").unwrap(), Color::Ansi(213));
+        assert_eq!(color("#ff8000").unwrap(), Color::Rgb(255, 128, 0));
+    }
+
+    #[test]
+    fn color_fails_on_unknown_values() {
+        assert!(color("purple").is_err());
+        assert!(color("256").is_err());
+        assert!(color("#ff80").is_err());
+        assert!(color("#gg8000").is_err());
+    }
+
+    #[test]
+    fn color_round_trips() {
+        for s in ["magenta", "213", "#ff8000"] {
+            assert_eq!(String::from(color(s).unwrap()), s);
+        }
+    }
+}